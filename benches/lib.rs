@@ -27,7 +27,7 @@ fn create_nbchan_mpsc(b: &mut Bencher) {
 #[bench]
 fn create_std_mpsc(b: &mut Bencher) {
     b.iter(|| {
-        std_mpsc::channel::<()>();
+        let _ = std_mpsc::channel::<()>();
     });
 }
 
@@ -35,7 +35,7 @@ fn create_std_mpsc(b: &mut Bencher) {
 fn clone_sender_nbchan_mpsc(b: &mut Bencher) {
     let (tx, _rx) = mpsc::channel::<()>();
     b.iter(|| {
-        tx.clone();
+        let _ = tx.clone();
     });
 }
 
@@ -43,7 +43,7 @@ fn clone_sender_nbchan_mpsc(b: &mut Bencher) {
 fn clone_sender_std_mpsc(b: &mut Bencher) {
     let (tx, _rx) = std_mpsc::channel::<()>();
     b.iter(|| {
-        tx.clone();
+        let _ = tx.clone();
     });
 }
 
@@ -80,6 +80,16 @@ fn oneshot_send_recv_nbchan_oneshot(b: &mut Bencher) {
     });
 }
 
+#[bench]
+fn oneshot_send_recv_nbchan_oneshot_pooled(b: &mut Bencher) {
+    let pool = oneshot::Pool::new();
+    b.iter(|| {
+        let (tx, mut rx) = oneshot::channel_in(&pool);
+        tx.send(1).unwrap();
+        assert!(rx.try_recv().is_ok());
+    });
+}
+
 #[bench]
 fn oneshot_send_recv_nbchan_mpsc(b: &mut Bencher) {
     b.iter(|| {
@@ -98,6 +108,24 @@ fn oneshot_send_recv_std_mpsc(b: &mut Bencher) {
     });
 }
 
+#[bench]
+fn oneshot_send_recv_large_nbchan_oneshot(b: &mut Bencher) {
+    b.iter(|| {
+        let (tx, mut rx) = oneshot::channel();
+        tx.send([0u8; 256]).unwrap();
+        assert!(rx.try_recv().is_ok());
+    });
+}
+
+#[bench]
+fn oneshot_send_recv_large_std_mpsc(b: &mut Bencher) {
+    b.iter(|| {
+        let (tx, rx) = std_mpsc::channel();
+        tx.send([0u8; 256]).unwrap();
+        assert!(rx.try_recv().is_ok());
+    });
+}
+
 #[bench]
 fn oneshot_multithread_send_recv_nbchan_oneshot(b: &mut Bencher) {
     let (txs_tx, txs_rx) = std_mpsc::sync_channel(2);
@@ -1,6 +1,30 @@
 extern crate nbchan;
 
+use std::alloc::{GlobalAlloc, Layout, System};
 use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAllocator;
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+fn count_allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    f();
+    ALLOCATIONS.load(Ordering::SeqCst) - before
+}
 
 fn main() {
     let (tx, rx) = nbchan::oneshot::channel::<()>();
@@ -22,4 +46,18 @@ fn main() {
         "std::sync::mpsc::Receiver<()>: {} bytes",
         mem::size_of_val(&rx)
     );
+
+    let n = count_allocations(|| {
+        let (tx, mut rx) = nbchan::oneshot::channel();
+        tx.send(1usize).unwrap();
+        rx.try_recv().unwrap();
+    });
+    println!("nbchan::oneshot send/recv:     {} allocations", n);
+
+    let n = count_allocations(|| {
+        let (tx, rx) = std::sync::mpsc::channel();
+        tx.send(1usize).unwrap();
+        rx.try_recv().unwrap();
+    });
+    println!("std::sync::mpsc send/recv:     {} allocations", n);
 }
@@ -0,0 +1,62 @@
+use std::error::Error;
+use std::fmt;
+
+/// An error returned from the `send_with` methods.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum SendWithError<T> {
+    /// The receiver already had dropped, so the value was not built.
+    Canceled,
+
+    /// The receiver dropped while the value was being built.
+    ///
+    /// The built value is returned back.
+    Disconnected(T),
+}
+impl<T> fmt::Debug for SendWithError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SendWithError::Canceled => write!(f, "Canceled"),
+            SendWithError::Disconnected(_) => write!(f, "Disconnected(..)"),
+        }
+    }
+}
+impl<T> fmt::Display for SendWithError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SendWithError::Canceled => write!(f, "sending on a canceled channel"),
+            SendWithError::Disconnected(_) => write!(f, "sending on a closed channel"),
+        }
+    }
+}
+impl<T> Error for SendWithError<T> {}
+
+/// An error returned from the `send_timeout` methods.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum SendTimeoutError<T> {
+    /// No slot became free before the timeout elapsed.
+    ///
+    /// The value is returned back.
+    Timeout(T),
+
+    /// The receiver has dropped.
+    ///
+    /// The value is returned back.
+    Disconnected(T),
+}
+impl<T> fmt::Debug for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SendTimeoutError::Timeout(_) => write!(f, "Timeout(..)"),
+            SendTimeoutError::Disconnected(_) => write!(f, "Disconnected(..)"),
+        }
+    }
+}
+impl<T> fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SendTimeoutError::Timeout(_) => write!(f, "timed out waiting on send operation"),
+            SendTimeoutError::Disconnected(_) => write!(f, "sending on a closed channel"),
+        }
+    }
+}
+impl<T> Error for SendTimeoutError<T> {}
@@ -1,7 +1,22 @@
 //! Highly optimized non-blocking communication channels.
 
 #![warn(missing_docs)]
+#[cfg(feature = "futures")]
+extern crate futures_core;
+#[cfg(feature = "futures")]
+extern crate futures_sink;
+
 pub mod mpsc;
 pub mod oneshot;
+pub mod rpc;
+
+#[cfg(feature = "metrics")]
+pub use metrics::ChannelStats;
 
+mod error;
+mod metrics;
+mod park;
 mod queue;
+#[cfg(test)]
+mod test_util;
+mod waker;
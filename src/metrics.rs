@@ -0,0 +1,109 @@
+#[cfg(feature = "metrics")]
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// A snapshot of the counters of a channel.
+///
+/// This is returned by the `stats` methods of the channel handles,
+/// which are available only if the `metrics` feature is enabled.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChannelStats {
+    /// The number of the values sent successfully.
+    pub sent: u64,
+
+    /// The number of the values received.
+    pub received: u64,
+
+    /// The number of the sending attempts rejected because the channel was full.
+    pub rejected_full: u64,
+
+    /// The number of the sending attempts failed because the receiver had gone.
+    ///
+    /// This includes the `send_with` calls which failed with `SendWithError::Canceled`.
+    pub failed_disconnected: u64,
+
+    /// The number of the values in the channel at the time of the snapshot.
+    pub depth: usize,
+
+    /// The largest number of the values that the channel has held at once.
+    pub peak_depth: usize,
+}
+
+/// The counters shared by the handles of a channel.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default)]
+pub struct Metrics {
+    sent: AtomicU64,
+    received: AtomicU64,
+    rejected_full: AtomicU64,
+    failed_disconnected: AtomicU64,
+    peak_depth: AtomicUsize,
+}
+#[cfg(feature = "metrics")]
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn record_sent(&self, count: usize) {
+        self.sent.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn record_received(&self, count: usize) {
+        self.received.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn record_full(&self) {
+        self.rejected_full.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn record_disconnected(&self) {
+        self.failed_disconnected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn record_depth(&self, depth: usize) {
+        self.peak_depth.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self, depth: usize) -> ChannelStats {
+        ChannelStats {
+            sent: self.sent.load(Ordering::Relaxed),
+            received: self.received.load(Ordering::Relaxed),
+            rejected_full: self.rejected_full.load(Ordering::Relaxed),
+            failed_disconnected: self.failed_disconnected.load(Ordering::Relaxed),
+            depth,
+            peak_depth: self.peak_depth.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The no-op replacement of the counters, used when the `metrics` feature is disabled.
+#[cfg(not(feature = "metrics"))]
+#[derive(Debug)]
+pub struct Metrics;
+#[cfg(not(feature = "metrics"))]
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics
+    }
+
+    #[inline(always)]
+    pub fn record_sent(&self, _count: usize) {}
+
+    #[inline(always)]
+    pub fn record_received(&self, _count: usize) {}
+
+    #[inline(always)]
+    pub fn record_full(&self) {}
+
+    #[inline(always)]
+    pub fn record_disconnected(&self) {}
+
+    #[inline(always)]
+    pub fn record_depth(&self, _depth: usize) {}
+}
@@ -1,38 +1,84 @@
 //! Multi-producer, single-consumer FIFO channel.
+//!
+//! With the `futures` feature enabled, [`Receiver`] implements `Stream` and
+//! [`Sender`] and [`SyncSender`] implement `Sink`.
 use std::cell::UnsafeCell;
+use std::collections::VecDeque;
 use std::fmt;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::mpsc::{SendError, TryRecvError, TrySendError};
-use std::sync::Arc;
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError, TryRecvError, TrySendError};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
-use queue::{self, QueueHead, QueueTail};
+#[cfg(feature = "futures")]
+use futures_core::Stream;
+#[cfg(feature = "futures")]
+use futures_sink::Sink;
+
+pub use error::{SendTimeoutError, SendWithError};
+
+#[cfg(feature = "metrics")]
+use metrics::ChannelStats;
+use metrics::Metrics;
+use park;
+use queue::{self, Batch, QueueHead, QueueTail, WeakQueueTail};
+use waker::AtomicWaker;
 
 /// Creates a new asynchronous channel, returning the sender/receiver halves.
 pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
     let (head, tail) = queue::fifo();
-    let queue_len = Arc::default();
+    let bound = Arc::new(Bound::new(UNBOUNDED));
     (
-        Sender { tail },
+        Sender {
+            tail,
+            bound: Arc::clone(&bound),
+        },
         Receiver {
             head: UnsafeCell::new(head),
-            queue_len,
+            bound,
         },
     )
 }
 
 /// Creates a new synchronous, bounded channel.
+///
+/// Unlike `std::sync::mpsc::sync_channel`, a bound of `0` does not make a rendezvous channel:
+/// such a channel can never hold a value, so sending on it always fails without waiting.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::mpsc::{SendError, TrySendError};
+/// use std::time::Duration;
+/// use nbchan::mpsc::{sync_channel, SendTimeoutError};
+///
+/// let (tx, _rx) = sync_channel(0);
+/// assert_eq!(tx.try_send(1), Err(TrySendError::Full(1)));
+/// assert_eq!(tx.send(2), Err(SendError(2)));
+/// assert_eq!(
+///     tx.send_timeout(3, Duration::from_secs(60)),
+///     Err(SendTimeoutError::Timeout(3))
+/// );
+/// ```
 pub fn sync_channel<T>(bound: usize) -> (SyncSender<T>, Receiver<T>) {
     let (head, tail) = queue::fifo();
-    let queue_len = Arc::default();
+    let bound = Arc::new(Bound::new(bound));
     (
         SyncSender {
-            inner: Sender { tail },
-            queue_len: Arc::clone(&queue_len),
-            queue_capacity: bound,
+            inner: Sender {
+                tail,
+                bound: Arc::clone(&bound),
+            },
+            waiter: None,
+            reserved: AtomicBool::new(false),
         },
         Receiver {
             head: UnsafeCell::new(head),
-            queue_len,
+            bound,
         },
     )
 }
@@ -40,22 +86,138 @@ pub fn sync_channel<T>(bound: usize) -> (SyncSender<T>, Receiver<T>) {
 /// The sending-half of an asynchronous channel.
 pub struct Sender<T> {
     tail: QueueTail<T>,
+    bound: Arc<Bound>,
 }
 impl<T> Sender<T> {
     /// Attempts to send a value on this channel, returning it back if it could not be sent.
     ///
     /// This method will never block the current thread.
     pub fn send(&self, item: T) -> Result<(), SendError<T>> {
+        // Counted before enqueuing, so that the receiver never sees the length go below zero.
+        self.bound.acquire_many(1);
+        self.enqueue(item).inspect_err(|_| self.bound.release())
+    }
+
+    /// Sends all the values of `items` at once, returning them back if they could not be sent.
+    ///
+    /// The values are linked to each other before they are published,
+    /// so this updates the shared tail of the queue only once.
+    /// Values sent by other senders never interleave with the batch.
+    ///
+    /// This method will never block the current thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nbchan::mpsc::channel;
+    ///
+    /// let (tx, rx) = channel();
+    /// tx.send_batch(vec![1, 2, 3]).unwrap();
+    /// assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 2, 3]);
+    /// ```
+    pub fn send_batch<I>(&self, items: I) -> Result<(), SendError<Vec<T>>>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let batch = Batch::new(items);
+        let len = batch.len();
+        self.bound.acquire_many(len);
+        self.enqueue_batch(batch).map_err(|batch| {
+            self.bound.release_many(len);
+            SendError(batch.into_vec())
+        })
+    }
+
+    /// Sends a value built by `f`, unless the receiver already has dropped.
+    ///
+    /// `f` is called only if the channel is still alive at the time of the call.
+    /// If it is not, this returns `SendWithError::Canceled` without calling `f`.
+    ///
+    /// This method will never block the current thread.
+    pub fn send_with<F>(&self, f: F) -> Result<(), SendWithError<T>>
+    where
+        F: FnOnce() -> T,
+    {
+        if self.is_disconnected() {
+            self.bound.metrics.record_disconnected();
+            Err(SendWithError::Canceled)
+        } else {
+            self.send(f())
+                .map_err(|SendError(item)| SendWithError::Disconnected(item))
+        }
+    }
+
+    /// Returns `true` if the receiver has dropped (or closed), otherwise `false`.
+    pub fn is_disconnected(&self) -> bool {
+        self.tail.is_disconnected()
+    }
+
+    /// Returns a snapshot of the counters of this channel.
+    ///
+    /// The counters are shared by all the handles of the channel,
+    /// so this can be used even after the receiver has dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nbchan::mpsc::channel;
+    ///
+    /// let (tx, rx) = channel();
+    /// tx.send(1).unwrap();
+    /// drop(rx);
+    /// assert!(tx.send(2).is_err());
+    ///
+    /// let stats = tx.stats();
+    /// assert_eq!((stats.sent, stats.failed_disconnected), (1, 1));
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> ChannelStats {
+        self.bound.metrics.snapshot(self.bound.len())
+    }
+
+    /// Creates a weak handle of this sender.
+    ///
+    /// Weak senders are not counted by [`Receiver::sender_count`], so the receiver
+    /// is disconnected once all the (strong) senders have dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::mpsc::TryRecvError;
+    /// use nbchan::mpsc::channel;
+    ///
+    /// let (tx, rx) = channel::<()>();
+    /// let weak = tx.downgrade();
+    /// assert!(weak.upgrade().is_some());
+    ///
+    /// drop(tx);
+    /// assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    /// assert!(weak.upgrade().is_none());
+    /// ```
+    pub fn downgrade(&self) -> WeakSender<T> {
+        WeakSender {
+            tail: self.tail.downgrade(),
+            bound: Arc::clone(&self.bound),
+        }
+    }
+
+    /// Enqueues `item` without touching the length counter.
+    fn enqueue(&self, item: T) -> Result<(), SendError<T>> {
         if let Some(item) = self.tail.enqueue(item) {
+            self.bound.metrics.record_disconnected();
             Err(SendError(item))
         } else {
+            self.bound.metrics.record_sent(1);
             Ok(())
         }
     }
 
-    /// Returns `true` if the receiver has dropped, otherwise `false`.
-    pub fn is_disconnected(&self) -> bool {
-        self.tail.is_disconnected()
+    fn enqueue_batch(&self, batch: Batch<T>) -> Result<(), Batch<T>> {
+        let len = batch.len();
+        self.tail
+            .enqueue_batch(batch)
+            .map(|()| self.bound.metrics.record_sent(len))
+            .inspect_err(|_| self.bound.metrics.record_disconnected())
     }
 }
 unsafe impl<T: Send> Send for Sender<T> {}
@@ -64,6 +226,22 @@ impl<T> Clone for Sender<T> {
     fn clone(&self) -> Self {
         Sender {
             tail: self.tail.clone(),
+            bound: Arc::clone(&self.bound),
+        }
+    }
+}
+impl<T> Extend<T> for Sender<T> {
+    /// Sends all the values of `iter`.
+    ///
+    /// If the receiver drops in the middle, the rest of the values are dropped.
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for item in iter {
+            if self.send(item).is_err() {
+                break;
+            }
         }
     }
 }
@@ -73,33 +251,344 @@ impl<T> fmt::Debug for Sender<T> {
     }
 }
 
+/// A sender which does not keep the channel connected.
+///
+/// This is created by the [`Sender::downgrade`] method.
+pub struct WeakSender<T> {
+    tail: WeakQueueTail<T>,
+    bound: Arc<Bound>,
+}
+impl<T> WeakSender<T> {
+    /// Returns a new sender, unless all the senders have dropped.
+    pub fn upgrade(&self) -> Option<Sender<T>> {
+        self.tail.upgrade().map(|tail| Sender {
+            tail,
+            bound: Arc::clone(&self.bound),
+        })
+    }
+}
+unsafe impl<T: Send> Send for WeakSender<T> {}
+unsafe impl<T: Send> Sync for WeakSender<T> {}
+impl<T> Clone for WeakSender<T> {
+    fn clone(&self) -> Self {
+        WeakSender {
+            tail: self.tail.clone(),
+            bound: Arc::clone(&self.bound),
+        }
+    }
+}
+impl<T> fmt::Debug for WeakSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WeakSender {{ .. }}")
+    }
+}
+
 /// The sending-half of an asynchronous channel.
 pub struct SyncSender<T> {
     inner: Sender<T>,
-    queue_len: Arc<AtomicUsize>,
-    queue_capacity: usize,
+    waiter: Option<Arc<Waiter>>,
+
+    // Whether a slot has been reserved by `poll_ready`.
+    reserved: AtomicBool,
 }
 impl<T> SyncSender<T> {
     /// Attempts to send a value on this channel.
     ///
     /// This method will never block the current thread.
+    ///
+    /// If other senders are waiting for a free slot, this fails with `TrySendError::Full`
+    /// so that it does not overtake them.
+    /// A slot reserved by [`poll_ready`](#method.poll_ready) is used if any.
+    /// Once the receiver has dropped (or closed), this fails with `TrySendError::Disconnected`
+    /// even if the channel is full.
     pub fn try_send(&self, item: T) -> Result<(), TrySendError<T>> {
-        let len = self.queue_len.fetch_add(1, Ordering::SeqCst);
-        if len >= self.queue_capacity {
-            self.queue_len.fetch_sub(1, Ordering::SeqCst);
+        if self.is_disconnected() {
+            self.inner.bound.metrics.record_disconnected();
+            Err(TrySendError::Disconnected(item))
+        } else if !self.reserved.swap(false, Ordering::SeqCst) && !self.inner.bound.try_acquire() {
+            self.inner.bound.metrics.record_full();
             Err(TrySendError::Full(item))
-        } else if let Err(SendError(item)) = self.inner.send(item) {
-            self.queue_len.fetch_sub(1, Ordering::SeqCst);
+        } else if let Err(SendError(item)) = self.inner.enqueue(item) {
+            self.inner.bound.release();
             Err(TrySendError::Disconnected(item))
         } else {
             Ok(())
         }
     }
 
-    /// Returns `true` if the receiver has dropped, otherwise `false`.
+    /// Attempts to send all the values of `items` at once.
+    ///
+    /// Either all the values are sent, or none of them are and they are returned back.
+    /// A batch larger than the capacity of the channel is always rejected with
+    /// `TrySendError::Full`.
+    ///
+    /// This method will never block the current thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::mpsc::TrySendError;
+    /// use nbchan::mpsc::sync_channel;
+    ///
+    /// let (tx, rx) = sync_channel(3);
+    /// assert_eq!(tx.try_send_batch(vec![1, 2]), Ok(()));
+    /// assert_eq!(
+    ///     tx.try_send_batch(vec![3, 4]),
+    ///     Err(TrySendError::Full(vec![3, 4]))
+    /// );
+    /// assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 2]);
+    /// ```
+    pub fn try_send_batch<I>(&self, items: I) -> Result<(), TrySendError<Vec<T>>>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let batch = Batch::new(items);
+        let len = batch.len();
+        if self.is_disconnected() {
+            self.inner.bound.metrics.record_disconnected();
+            Err(TrySendError::Disconnected(batch.into_vec()))
+        } else if !self.inner.bound.try_acquire_many(len) {
+            self.inner.bound.metrics.record_full();
+            Err(TrySendError::Full(batch.into_vec()))
+        } else if let Err(batch) = self.inner.enqueue_batch(batch) {
+            self.inner.bound.release_many(len);
+            Err(TrySendError::Disconnected(batch.into_vec()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Sends a value, blocking the current thread until a slot is free.
+    ///
+    /// This fails with the value returned if the receiver has dropped,
+    /// or immediately if the bound of the channel is `0`.
+    /// Blocked senders are given free slots in the order they started waiting.
+    /// A slot reserved by [`poll_ready`](#method.poll_ready) is used if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::thread;
+    /// use nbchan::mpsc::sync_channel;
+    ///
+    /// let (tx, rx) = sync_channel(1);
+    /// let handle = thread::spawn(move || {
+    ///     for i in 0..3 {
+    ///         tx.send(i).unwrap();
+    ///     }
+    /// });
+    /// for i in 0..3 {
+    ///     assert_eq!(rx.recv(), Ok(i));
+    /// }
+    /// handle.join().unwrap();
+    /// ```
+    pub fn send(&self, item: T) -> Result<(), SendError<T>> {
+        self.send_until(item, None).map_err(|e| match e {
+            SendTimeoutError::Timeout(item) | SendTimeoutError::Disconnected(item) => {
+                SendError(item)
+            }
+        })
+    }
+
+    /// Sends a value, blocking the current thread until a slot is free or `timeout` has elapsed.
+    ///
+    /// If the bound of the channel is `0`, this fails with `SendTimeoutError::Timeout`
+    /// without waiting.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use nbchan::mpsc::{sync_channel, SendTimeoutError};
+    ///
+    /// let (tx, _rx) = sync_channel(1);
+    /// assert_eq!(tx.send_timeout(1, Duration::from_millis(1)), Ok(()));
+    /// assert_eq!(
+    ///     tx.send_timeout(2, Duration::from_millis(1)),
+    ///     Err(SendTimeoutError::Timeout(2))
+    /// );
+    /// ```
+    pub fn send_timeout(&self, item: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
+        self.send_until(item, Instant::now().checked_add(timeout))
+    }
+
+    /// Polls for a free slot, registering the current task to be woken when one is available.
+    ///
+    /// Once this returns `Poll::Ready(())`, a slot is reserved for the next value sent by
+    /// [`send_async`](#method.send_async) (or by `Sink::start_send`).
+    /// It also returns `Poll::Ready(())` if the receiver has dropped or the bound of the channel
+    /// is `0`, and then the next sending fails.
+    ///
+    /// Waiting senders are given free slots in the order they started waiting.
+    pub fn poll_ready(&mut self, cx: &mut Context) -> Poll<()> {
+        if *self.reserved.get_mut() || self.is_disconnected() || self.capacity() == 0 {
+            return Poll::Ready(());
+        }
+        if self.waiter.is_none() {
+            if self.inner.bound.try_acquire() {
+                *self.reserved.get_mut() = true;
+                return Poll::Ready(());
+            }
+            self.waiter = Some(self.inner.bound.enqueue_waiter());
+        }
+
+        let state = {
+            let waiter = self.waiter.as_ref().expect("Never fails");
+            waiter.task.register(cx.waker());
+            waiter.state.load(Ordering::SeqCst)
+        };
+        if state == WAITER_WAITING {
+            Poll::Pending
+        } else {
+            self.waiter = None;
+            *self.reserved.get_mut() = state == WAITER_GRANTED;
+            Poll::Ready(())
+        }
+    }
+
+    /// Returns a future that sends `item` once a slot is free.
+    ///
+    /// The future fails with the item returned if the receiver has dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::future::Future;
+    /// use std::pin::pin;
+    /// use std::task::{Context, Poll, Waker};
+    /// use nbchan::mpsc::sync_channel;
+    ///
+    /// let (mut tx, rx) = sync_channel(1);
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// assert_eq!(pin!(tx.send_async(1)).poll(&mut cx), Poll::Ready(Ok(())));
+    ///
+    /// let mut future = pin!(tx.send_async(2));
+    /// assert!(future.as_mut().poll(&mut cx).is_pending());
+    /// assert_eq!(rx.try_recv(), Ok(1));
+    /// assert_eq!(future.poll(&mut cx), Poll::Ready(Ok(())));
+    /// assert_eq!(rx.try_recv(), Ok(2));
+    /// ```
+    pub fn send_async(&mut self, item: T) -> SendAsync<'_, T> {
+        SendAsync {
+            sender: self,
+            item: Some(item),
+            owns_ready: false,
+        }
+    }
+
+    /// Returns the number of the values sent on this channel and not received yet.
+    ///
+    /// Slots reserved by senders (including those reserved by `poll_ready`) are included.
+    pub fn len(&self) -> usize {
+        self.inner.bound.len()
+    }
+
+    /// Returns `true` if there are no values waiting to be received, otherwise `false`.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the bound of this channel.
+    pub fn capacity(&self) -> usize {
+        self.inner.bound.queue_capacity
+    }
+
+    /// Returns the number of the values that can be sent without waiting.
+    ///
+    /// This is `0` once the receiver has dropped (or closed).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nbchan::mpsc::sync_channel;
+    ///
+    /// let (tx, _rx) = sync_channel(2);
+    /// tx.try_send(1).unwrap();
+    /// assert_eq!(tx.capacity(), 2);
+    /// assert_eq!(tx.len(), 1);
+    /// assert_eq!(tx.remaining(), 1);
+    /// ```
+    pub fn remaining(&self) -> usize {
+        if self.is_disconnected() {
+            0
+        } else {
+            self.capacity().saturating_sub(self.len())
+        }
+    }
+
+    /// Returns `true` if the receiver has dropped (or closed), otherwise `false`.
     pub fn is_disconnected(&self) -> bool {
         self.inner.is_disconnected()
     }
+
+    /// Returns a snapshot of the counters of this channel.
+    ///
+    /// See [`Sender::stats`] for details.
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> ChannelStats {
+        self.inner.stats()
+    }
+
+    fn send_until(&self, item: T, deadline: Option<Instant>) -> Result<(), SendTimeoutError<T>> {
+        if self.is_disconnected() {
+            self.inner.bound.metrics.record_disconnected();
+            return Err(SendTimeoutError::Disconnected(item));
+        }
+        if self.capacity() == 0 {
+            // No slot will ever be freed.
+            self.inner.bound.metrics.record_full();
+            return Err(SendTimeoutError::Timeout(item));
+        }
+        if !self.reserved.swap(false, Ordering::SeqCst) && !self.inner.bound.try_acquire() {
+            let waiter = self.inner.bound.enqueue_waiter();
+            let waker = park::thread_waker();
+            loop {
+                waiter.task.register(&waker);
+                match waiter.state.load(Ordering::SeqCst) {
+                    WAITER_GRANTED => break,
+                    WAITER_CLOSED => {
+                        self.inner.bound.metrics.record_disconnected();
+                        return Err(SendTimeoutError::Disconnected(item));
+                    }
+                    _ => {}
+                }
+                if !park::park_until(deadline) {
+                    self.inner.bound.cancel(&waiter);
+                    return Err(SendTimeoutError::Timeout(item));
+                }
+            }
+        }
+        self.inner.enqueue(item).map_err(|SendError(item)| {
+            self.inner.bound.release();
+            SendTimeoutError::Disconnected(item)
+        })
+    }
+
+    fn send_reserved(&mut self, item: T) -> Result<(), SendError<T>> {
+        if !*self.reserved.get_mut() {
+            if self.capacity() == 0 {
+                self.inner.bound.metrics.record_full();
+            } else {
+                self.inner.bound.metrics.record_disconnected();
+            }
+            return Err(SendError(item));
+        }
+        *self.reserved.get_mut() = false;
+        self.inner
+            .enqueue(item)
+            .inspect_err(|_| self.inner.bound.release())
+    }
+
+    /// Gives up the slot reserved (or being waited for) by `poll_ready`.
+    fn cancel_ready(&mut self) {
+        if *self.reserved.get_mut() {
+            *self.reserved.get_mut() = false;
+            self.inner.bound.release();
+        }
+        if let Some(waiter) = self.waiter.take() {
+            self.inner.bound.cancel(&waiter);
+        }
+    }
 }
 unsafe impl<T: Send> Send for SyncSender<T> {}
 unsafe impl<T: Send> Sync for SyncSender<T> {}
@@ -107,8 +596,28 @@ impl<T> Clone for SyncSender<T> {
     fn clone(&self) -> Self {
         SyncSender {
             inner: self.inner.clone(),
-            queue_len: Arc::clone(&self.queue_len),
-            queue_capacity: self.queue_capacity,
+            waiter: None,
+            reserved: AtomicBool::new(false),
+        }
+    }
+}
+impl<T> Drop for SyncSender<T> {
+    fn drop(&mut self) {
+        self.cancel_ready();
+    }
+}
+impl<T> Extend<T> for SyncSender<T> {
+    /// Sends all the values of `iter`, blocking the current thread while the channel is full.
+    ///
+    /// If the receiver drops in the middle, the rest of the values are dropped.
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for item in iter {
+            if SyncSender::send(self, item).is_err() {
+                break;
+            }
         }
     }
 }
@@ -118,10 +627,51 @@ impl<T> fmt::Debug for SyncSender<T> {
     }
 }
 
+/// A future which sends a value once the channel has a free slot.
+///
+/// This is created by the [`SyncSender::send_async`] method.
+pub struct SendAsync<'a, T: 'a> {
+    sender: &'a mut SyncSender<T>,
+    item: Option<T>,
+
+    // Whether the slot reserved (or being waited for) by the sender has been requested by this future.
+    owns_ready: bool,
+}
+impl<'a, T> Future for SendAsync<'a, T> {
+    type Output = Result<(), SendError<T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if !this.owns_ready {
+            // A slot reserved by the caller before this future was created is not ours to give up.
+            this.owns_ready = !*this.sender.reserved.get_mut() && this.sender.waiter.is_none();
+        }
+        if this.sender.poll_ready(cx).is_pending() {
+            return Poll::Pending;
+        }
+        let item = this.item.take().expect("Cannot poll SendAsync twice");
+        Poll::Ready(this.sender.send_reserved(item))
+    }
+}
+impl<'a, T> Drop for SendAsync<'a, T> {
+    fn drop(&mut self) {
+        if self.item.is_some() && self.owns_ready {
+            // Otherwise the slot would be kept until the sender dropped.
+            self.sender.cancel_ready();
+        }
+    }
+}
+impl<'a, T> Unpin for SendAsync<'a, T> {}
+impl<'a, T> fmt::Debug for SendAsync<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SendAsync {{ .. }}")
+    }
+}
+
 /// The receiving-half of an asynchronous channel.
 pub struct Receiver<T> {
     head: UnsafeCell<QueueHead<T>>,
-    queue_len: Arc<AtomicUsize>,
+    bound: Arc<Bound>,
 }
 impl<T> Receiver<T> {
     /// Attempts to return a pending value on this receiver without blocking.
@@ -129,28 +679,682 @@ impl<T> Receiver<T> {
     /// This method will never block the current thread.
     pub fn try_recv(&self) -> Result<T, TryRecvError> {
         let head = unsafe { &mut *self.head.get() };
-        if let Some(item) = head.dequeue() {
-            self.queue_len.fetch_sub(1, Ordering::SeqCst);
-            Ok(item)
-        } else if head.is_tail_alive() {
-            Err(TryRecvError::Empty)
+        let item = match head.dequeue() {
+            Some(item) => item,
+            None if !head.is_disconnected() => return Err(TryRecvError::Empty),
+            // The last sender may have sent a value just before it dropped.
+            None => head.dequeue().ok_or(TryRecvError::Disconnected)?,
+        };
+        self.bound.release_received(1);
+        Ok(item)
+    }
+
+    /// Closes this receiver without dropping the pending values.
+    ///
+    /// After this call, sending on this channel fails and the value is returned back,
+    /// and the senders blocked on a bounded channel are woken up.
+    /// The values sent before the call still can be received,
+    /// and then `TryRecvError::Disconnected` is reported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::mpsc::{SendError, TryRecvError};
+    /// use nbchan::mpsc::channel;
+    ///
+    /// let (tx, rx) = channel();
+    /// tx.send(1).unwrap();
+    /// rx.close();
+    /// assert_eq!(tx.send(2), Err(SendError(2)));
+    ///
+    /// assert_eq!(rx.try_recv(), Ok(1));
+    /// assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    /// ```
+    pub fn close(&self) {
+        unsafe { &mut *self.head.get() }.close();
+        self.bound.close();
+    }
+
+    /// Returns the number of the values sent on this channel and not received yet.
+    ///
+    /// Values being sent by other threads concurrently may be included,
+    /// as well as slots reserved by senders of a bounded channel (e.g., by `poll_ready`).
+    ///
+    /// Unbounded channels do not keep a length counter (unless the `metrics` feature is enabled),
+    /// so this counts the pending values one by one,
+    /// and the senders of such channels cannot tell the length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nbchan::mpsc::channel;
+    ///
+    /// let (tx, rx) = channel();
+    /// assert!(rx.is_empty());
+    /// tx.send(1).unwrap();
+    /// tx.send(2).unwrap();
+    /// assert_eq!(rx.len(), 2);
+    /// assert_eq!(rx.try_recv(), Ok(1));
+    /// assert_eq!(rx.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        if self.bound.is_counted() {
+            self.bound.len()
         } else {
-            Err(TryRecvError::Disconnected)
+            unsafe { &*self.head.get() }.pending_count()
+        }
+    }
+
+    /// Returns `true` if there are no values waiting to be received, otherwise `false`.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of the live senders of this channel.
+    ///
+    /// Weak senders are not included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nbchan::mpsc::channel;
+    ///
+    /// let (tx, rx) = channel::<()>();
+    /// let tx2 = tx.clone();
+    /// let _weak = tx.downgrade();
+    /// assert_eq!(rx.sender_count(), 2);
+    ///
+    /// drop(tx);
+    /// drop(tx2);
+    /// assert_eq!(rx.sender_count(), 0);
+    /// ```
+    pub fn sender_count(&self) -> usize {
+        unsafe { &*self.head.get() }.tail_count()
+    }
+
+    /// Returns a snapshot of the counters of this channel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nbchan::mpsc::sync_channel;
+    ///
+    /// let (tx, rx) = sync_channel(1);
+    /// tx.try_send(1).unwrap();
+    /// assert!(tx.try_send(2).is_err());
+    /// assert_eq!(rx.try_recv(), Ok(1));
+    ///
+    /// let stats = rx.stats();
+    /// assert_eq!((stats.sent, stats.received, stats.rejected_full), (1, 1, 1));
+    /// assert_eq!((stats.depth, stats.peak_depth), (0, 1));
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> ChannelStats {
+        self.bound.metrics.snapshot(self.bound.len())
+    }
+
+    /// Polls for a value, registering the current task to be woken when one is sent.
+    ///
+    /// Returns `Poll::Ready(Err(RecvError))` once all the senders have dropped
+    /// and no values are pending.
+    pub fn poll_recv(&self, cx: &mut Context) -> Poll<Result<T, RecvError>> {
+        match self.try_recv() {
+            Err(TryRecvError::Empty) => {}
+            result => return Poll::Ready(result.map_err(|_| RecvError)),
+        }
+        let head = unsafe { &*self.head.get() };
+        head.register_waker(cx.waker());
+        match self.try_recv() {
+            Err(TryRecvError::Empty) => Poll::Pending,
+            result => {
+                head.unregister_waker();
+                Poll::Ready(result.map_err(|_| RecvError))
+            }
         }
     }
+
+    /// Moves at most `max` pending values into `buf`, returning the number of the moved values.
+    ///
+    /// Unlike calling [`try_recv`](#method.try_recv) repeatedly, this updates
+    /// the shared length counter only once for the whole batch.
+    /// It returns `0` both if the channel is empty and if all the senders have dropped.
+    ///
+    /// This method will never block the current thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nbchan::mpsc::channel;
+    ///
+    /// let (tx, rx) = channel();
+    /// for i in 0..5 {
+    ///     tx.send(i).unwrap();
+    /// }
+    ///
+    /// let mut buf = Vec::new();
+    /// assert_eq!(rx.try_recv_many(&mut buf, 3), 3);
+    /// assert_eq!(rx.try_recv_many(&mut buf, 3), 2);
+    /// assert_eq!(buf, [0, 1, 2, 3, 4]);
+    /// ```
+    pub fn try_recv_many(&self, buf: &mut Vec<T>, max: usize) -> usize {
+        let head = unsafe { &mut *self.head.get() };
+        let count = head.dequeue_many(buf, max);
+        if count != 0 {
+            self.bound.release_received(count);
+        }
+        count
+    }
+
+    /// Returns an iterator that takes the values pending on this receiver, without blocking.
+    ///
+    /// The iterator yields values until it finds the channel empty.
+    /// Unlike [`try_iter`](#method.try_iter), it updates the shared length counter only once
+    /// for all the yielded values, when it is exhausted or dropped;
+    /// until then, the slots of the yielded values are not freed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nbchan::mpsc::channel;
+    ///
+    /// let (tx, rx) = channel();
+    /// tx.send(1).unwrap();
+    /// tx.send(2).unwrap();
+    /// assert_eq!(rx.drain().collect::<Vec<_>>(), [1, 2]);
+    /// ```
+    pub fn drain(&self) -> Drain<'_, T> {
+        Drain { rx: self, count: 0 }
+    }
+
+    /// Returns an iterator that yields the values pending at the moment, without blocking.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nbchan::mpsc::channel;
+    ///
+    /// let (tx, rx) = channel();
+    /// tx.send(1).unwrap();
+    /// tx.send(2).unwrap();
+    /// assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 2]);
+    /// assert_eq!(rx.try_iter().next(), None);
+    /// ```
+    pub fn try_iter(&self) -> TryIter<'_, T> {
+        TryIter { rx: self }
+    }
+
+    /// Returns an iterator that blocks waiting for values until all the senders have dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::thread;
+    /// use nbchan::mpsc::channel;
+    ///
+    /// let (tx, rx) = channel();
+    /// thread::spawn(move || {
+    ///     tx.send(1).unwrap();
+    ///     tx.send(2).unwrap();
+    /// });
+    /// assert_eq!(rx.iter().collect::<Vec<_>>(), [1, 2]);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { rx: self }
+    }
+
+    /// Blocks the current thread until a value is received or all the senders have dropped.
+    ///
+    /// Values sent before the last sender dropped are still received.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::mpsc::RecvError;
+    /// use std::thread;
+    /// use nbchan::mpsc::channel;
+    ///
+    /// let (tx, rx) = channel();
+    /// thread::spawn(move || tx.send(1).unwrap());
+    /// assert_eq!(rx.recv(), Ok(1));
+    /// assert_eq!(rx.recv(), Err(RecvError));
+    /// ```
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.recv_until(None).map_err(|_| RecvError)
+    }
+
+    /// Blocks the current thread until a value is received, all the senders have dropped,
+    /// or `timeout` has elapsed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::mpsc::RecvTimeoutError;
+    /// use std::time::Duration;
+    /// use nbchan::mpsc::channel;
+    ///
+    /// let (_tx, rx) = channel::<()>();
+    /// assert_eq!(
+    ///     rx.recv_timeout(Duration::from_millis(1)),
+    ///     Err(RecvTimeoutError::Timeout)
+    /// );
+    /// ```
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.recv_until(Some(deadline)),
+            None => self.recv_until(None),
+        }
+    }
+
+    /// Blocks the current thread until a value is received, all the senders have dropped,
+    /// or `deadline` is reached.
+    pub fn recv_deadline(&self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        self.recv_until(Some(deadline))
+    }
+
+    fn recv_until(&self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        match self.try_recv() {
+            Ok(item) => return Ok(item),
+            Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+            Err(TryRecvError::Empty) => {}
+        }
+
+        let head = unsafe { &*self.head.get() };
+        let waker = park::thread_waker();
+        let result = loop {
+            head.register_waker(&waker);
+            match self.try_recv() {
+                Ok(item) => break Ok(item),
+                Err(TryRecvError::Disconnected) => break Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => {}
+            }
+            if !park::park_until(deadline) {
+                break Err(RecvTimeoutError::Timeout);
+            }
+        };
+
+        // Otherwise every following sending would wake this thread in vain.
+        head.unregister_waker();
+        result
+    }
 }
 unsafe impl<T: Send> Send for Receiver<T> {}
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let dropped = self.head.get_mut().clear();
+        self.bound.close();
+        self.bound.release_many(dropped);
+    }
+}
 impl<T> fmt::Debug for Receiver<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Receiver {{ .. }}")
     }
 }
+impl<'a, T> IntoIterator for &'a Receiver<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+impl<T> IntoIterator for Receiver<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { rx: self }
+    }
+}
+
+/// An iterator over the values pending on a receiver.
+///
+/// This is created by the [`Receiver::try_iter`] method.
+pub struct TryIter<'a, T: 'a> {
+    rx: &'a Receiver<T>,
+}
+impl<'a, T> Iterator for TryIter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.try_recv().ok()
+    }
+}
+impl<'a, T> fmt::Debug for TryIter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TryIter {{ .. }}")
+    }
+}
+
+/// A draining iterator over the values pending on a receiver.
+///
+/// This is created by the [`Receiver::drain`] method.
+pub struct Drain<'a, T: 'a> {
+    rx: &'a Receiver<T>,
+
+    // The number of the values yielded and not subtracted from the length counter yet.
+    count: usize,
+}
+impl<'a, T> Drain<'a, T> {
+    fn release(&mut self) {
+        if self.count != 0 {
+            self.rx.bound.release_received(self.count);
+            self.count = 0;
+        }
+    }
+}
+impl<'a, T> Iterator for Drain<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let head = unsafe { &mut *self.rx.head.get() };
+        if let Some(item) = head.dequeue() {
+            self.count += 1;
+            Some(item)
+        } else {
+            self.release();
+            None
+        }
+    }
+}
+impl<'a, T> Drop for Drain<'a, T> {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+impl<'a, T> fmt::Debug for Drain<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Drain {{ .. }}")
+    }
+}
+
+/// A blocking iterator over the values received on a receiver.
+///
+/// This is created by the [`Receiver::iter`] method.
+pub struct Iter<'a, T: 'a> {
+    rx: &'a Receiver<T>,
+}
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+impl<'a, T> fmt::Debug for Iter<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Iter {{ .. }}")
+    }
+}
+
+/// An owning blocking iterator over the values received on a receiver.
+///
+/// This is created by the `into_iter` method of [`Receiver`].
+pub struct IntoIter<T> {
+    rx: Receiver<T>,
+}
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+impl<T> fmt::Debug for IntoIter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "IntoIter {{ .. }}")
+    }
+}
+
+/// The capacity of the channels created by `channel()`.
+const UNBOUNDED: usize = usize::MAX;
+
+const WAITER_WAITING: usize = 0;
+const WAITER_GRANTED: usize = 1;
+const WAITER_CLOSED: usize = 2;
+
+/// A sender waiting for a free slot.
+#[derive(Debug)]
+struct Waiter {
+    state: AtomicUsize,
+    task: AtomicWaker,
+}
+
+#[derive(Debug, Default)]
+struct Waiters {
+    queue: VecDeque<Arc<Waiter>>,
+    closed: bool,
+}
+
+/// The number of the values in a channel, and the senders waiting for it to decrease.
+///
+/// The waiter list is only locked if some senders are waiting.
+/// Unbounded channels only use the length counter, and only if the `metrics` feature needs it.
+#[derive(Debug)]
+struct Bound {
+    queue_len: AtomicUsize,
+    queue_capacity: usize,
+    waiter_count: AtomicUsize,
+    waiters: Mutex<Waiters>,
+    metrics: Metrics,
+}
+impl Bound {
+    fn new(queue_capacity: usize) -> Self {
+        Bound {
+            queue_len: AtomicUsize::new(0),
+            queue_capacity,
+            waiter_count: AtomicUsize::new(0),
+            waiters: Mutex::default(),
+            metrics: Metrics::new(),
+        }
+    }
+
+    /// Reserves a slot, unless it is full or some senders are waiting.
+    #[inline]
+    fn try_acquire(&self) -> bool {
+        self.try_acquire_many(1)
+    }
+
+    #[inline]
+    fn try_acquire_many(&self, count: usize) -> bool {
+        self.waiter_count.load(Ordering::SeqCst) == 0 && self.reserve(count)
+    }
+
+    /// Frees a slot, handing it over to the first waiting sender (if any).
+    #[inline]
+    fn release(&self) {
+        self.release_many(1);
+    }
+
+    #[inline]
+    fn release_received(&self, count: usize) {
+        self.metrics.record_received(count);
+        self.release_many(count);
+    }
+
+    #[inline]
+    fn release_many(&self, count: usize) {
+        if !self.is_counted() {
+            return;
+        }
+        self.queue_len.fetch_sub(count, Ordering::SeqCst);
+        if self.queue_capacity != UNBOUNDED && self.waiter_count.load(Ordering::SeqCst) != 0 {
+            let granted = self.grant(&mut self.waiters());
+            wake_all(granted);
+        }
+    }
+
+    /// Counts values sent on an unbounded channel.
+    #[inline]
+    fn acquire_many(&self, count: usize) {
+        if self.is_counted() {
+            let len = self.queue_len.fetch_add(count, Ordering::SeqCst);
+            self.metrics.record_depth(len + count);
+        }
+    }
+
+    /// Returns `true` if `queue_len` is maintained, otherwise `false`.
+    #[inline]
+    fn is_counted(&self) -> bool {
+        self.queue_capacity != UNBOUNDED || cfg!(feature = "metrics")
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.queue_len.load(Ordering::SeqCst)
+    }
+
+    fn enqueue_waiter(&self) -> Arc<Waiter> {
+        let waiter = Arc::new(Waiter {
+            state: AtomicUsize::new(WAITER_WAITING),
+            task: AtomicWaker::new(),
+        });
+        let mut waiters = self.waiters();
+        if waiters.closed {
+            waiter.state.store(WAITER_CLOSED, Ordering::SeqCst);
+        } else {
+            waiters.queue.push_back(Arc::clone(&waiter));
+            self.waiter_count.fetch_add(1, Ordering::SeqCst);
+
+            // A slot may have been freed before the above increment was observed.
+            let granted = self.grant(&mut waiters);
+            mem::drop(waiters);
+            wake_all(granted);
+        }
+        waiter
+    }
+
+    fn cancel(&self, waiter: &Arc<Waiter>) {
+        let mut waiters = self.waiters();
+        if let Some(i) = waiters.queue.iter().position(|w| Arc::ptr_eq(w, waiter)) {
+            waiters.queue.remove(i);
+            self.waiter_count.fetch_sub(1, Ordering::SeqCst);
+        } else if waiter.state.load(Ordering::SeqCst) == WAITER_GRANTED {
+            self.queue_len.fetch_sub(1, Ordering::SeqCst);
+            let granted = self.grant(&mut waiters);
+            mem::drop(waiters);
+            wake_all(granted);
+        }
+    }
+
+    fn close(&self) {
+        let closed = {
+            let mut waiters = self.waiters();
+            waiters.closed = true;
+            self.waiter_count.store(0, Ordering::SeqCst);
+            for waiter in &waiters.queue {
+                waiter.state.store(WAITER_CLOSED, Ordering::SeqCst);
+            }
+            waiters.queue.drain(..).collect()
+        };
+        wake_all(closed);
+    }
+
+    /// Hands free slots over to the waiting senders, returning the ones to be woken.
+    ///
+    /// The caller must wake them after unlocking `waiters`,
+    /// because waking may drop a sender which locks `waiters` again.
+    fn grant(&self, waiters: &mut Waiters) -> Vec<Arc<Waiter>> {
+        let mut granted = Vec::new();
+        while !waiters.queue.is_empty() && self.reserve(1) {
+            let waiter = waiters.queue.pop_front().expect("Never fails");
+            self.waiter_count.fetch_sub(1, Ordering::SeqCst);
+            waiter.state.store(WAITER_GRANTED, Ordering::SeqCst);
+            granted.push(waiter);
+        }
+        granted
+    }
+
+    fn reserve(&self, count: usize) -> bool {
+        let mut len = self.queue_len.load(Ordering::SeqCst);
+        while self.queue_capacity.saturating_sub(len) >= count {
+            match self.queue_len.compare_exchange(
+                len,
+                len + count,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => {
+                    self.metrics.record_depth(len + count);
+                    return true;
+                }
+                Err(actual) => len = actual,
+            }
+        }
+        false
+    }
+
+    fn waiters(&self) -> MutexGuard<'_, Waiters> {
+        self.waiters.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn wake_all(waiters: Vec<Arc<Waiter>>) {
+    for waiter in waiters {
+        waiter.task.wake();
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+        self.poll_recv(cx).map(Result::ok)
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<T> Sink<T> for Sender<T> {
+    type Error = SendError<T>;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        self.send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<T> Sink<T> for SyncSender<T> {
+    type Error = TrySendError<T>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        SyncSender::poll_ready(self.get_mut(), cx).map(Ok)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        self.get_mut().try_send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
 
 #[cfg(test)]
 mod test {
     use super::*;
     use std::mem;
+    use std::pin::pin;
     use std::sync::mpsc::{SendError, TryRecvError, TrySendError};
+    use std::task::{Wake, Waker};
+    use std::thread;
+    use test_util::counting_waker;
 
     #[test]
     fn async_channel_works() {
@@ -169,6 +1373,23 @@ mod test {
         assert!(tx.is_disconnected());
     }
 
+    #[test]
+    fn send_with_works() {
+        let (tx, rx) = channel();
+        assert_eq!(tx.send_with(|| 3), Ok(()));
+        assert_eq!(rx.try_recv(), Ok(3));
+
+        let result = tx.send_with(|| {
+            mem::drop(rx);
+            4
+        });
+        assert_eq!(result, Err(SendWithError::Disconnected(4)));
+        assert_eq!(
+            tx.send_with(|| panic!("never called")),
+            Err(SendWithError::Canceled)
+        );
+    }
+
     #[test]
     fn sync_channel_works() {
         let (tx, rx) = sync_channel::<usize>(1);
@@ -188,4 +1409,581 @@ mod test {
         assert_eq!(tx.try_send(3), Err(TrySendError::Disconnected(3)));
         assert!(tx.is_disconnected());
     }
+
+    #[test]
+    fn recv_works() {
+        let (tx, rx) = channel();
+        let handle = thread::spawn(move || {
+            for i in 0..100 {
+                tx.send(i).unwrap();
+                if i % 10 == 0 {
+                    thread::yield_now();
+                }
+            }
+        });
+        for i in 0..100 {
+            assert_eq!(rx.recv(), Ok(i));
+        }
+        assert_eq!(rx.recv(), Err(RecvError));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn recv_timeout_works() {
+        let (tx, rx) = sync_channel(1);
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(1)),
+            Err(RecvTimeoutError::Timeout)
+        );
+
+        tx.try_send(1).unwrap();
+        assert_eq!(rx.recv_timeout(Duration::from_millis(1)), Ok(1));
+
+        let tx2 = tx.clone();
+        thread::spawn(move || mem::drop(tx2));
+        mem::drop(tx);
+        assert_eq!(
+            rx.recv_deadline(Instant::now() + Duration::from_secs(60)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn stream_works_with_many_producers() {
+        use futures_core::Stream;
+        use futures_sink::Sink;
+        use park;
+        use std::pin::Pin;
+        use std::task::{Context, Poll};
+
+        let (tx, mut rx) = channel();
+        let handles = (0..8)
+            .map(|i| {
+                let mut tx = tx.clone();
+                thread::spawn(move || {
+                    let waker = park::thread_waker();
+                    let mut cx = Context::from_waker(&waker);
+                    for j in 0..100 {
+                        let mut tx = Pin::new(&mut tx);
+                        assert!(tx.as_mut().poll_ready(&mut cx).is_ready());
+                        tx.as_mut().start_send(i * 100 + j).unwrap();
+                        if j % 10 == 0 {
+                            thread::yield_now();
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        mem::drop(tx);
+
+        let waker = park::thread_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut values = Vec::new();
+        loop {
+            match Pin::new(&mut rx).poll_next(&mut cx) {
+                Poll::Ready(Some(v)) => values.push(v),
+                Poll::Ready(None) => break,
+                Poll::Pending => {
+                    park::park_until(None);
+                }
+            }
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        values.sort();
+        assert_eq!(values, (0..800).collect::<Vec<_>>());
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn sync_sink_works() {
+        use futures_sink::Sink;
+        use std::pin::Pin;
+        use std::task::{Context, Poll, Waker};
+
+        let (mut tx, rx) = sync_channel(1);
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(Pin::new(&mut tx).poll_ready(&mut cx), Poll::Ready(Ok(())));
+        assert_eq!(tx.clone().try_send(1), Err(TrySendError::Full(1)));
+        assert_eq!(Pin::new(&mut tx).start_send(2), Ok(()));
+        assert_eq!(Pin::new(&mut tx).poll_ready(&mut cx), Poll::Pending);
+
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(Pin::new(&mut tx).poll_ready(&mut cx), Poll::Ready(Ok(())));
+        mem::drop(rx);
+        assert_eq!(
+            Pin::new(&mut tx).start_send(3),
+            Err(TrySendError::Disconnected(3))
+        );
+    }
+
+    #[test]
+    fn send_async_works() {
+        let (mut tx, rx) = sync_channel(1);
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(
+            Pin::new(&mut tx.send_async(1)).poll(&mut cx),
+            Poll::Ready(Ok(()))
+        );
+
+        let mut tx2 = tx.clone();
+        let (waker, woken) = counting_waker();
+        let mut cx2 = Context::from_waker(&waker);
+        {
+            let mut future = tx2.send_async(2);
+            assert!(Pin::new(&mut future).poll(&mut cx2).is_pending());
+            assert_eq!(woken.load(Ordering::SeqCst), 0);
+
+            assert_eq!(rx.try_recv(), Ok(1));
+            assert_eq!(woken.load(Ordering::SeqCst), 1);
+            assert_eq!(Pin::new(&mut future).poll(&mut cx2), Poll::Ready(Ok(())));
+        }
+        assert_eq!(rx.try_recv(), Ok(2));
+
+        assert_eq!(tx.try_send(3), Ok(()));
+        let mut future = tx2.send_async(4);
+        assert!(Pin::new(&mut future).poll(&mut cx2).is_pending());
+        mem::drop(rx);
+        assert_eq!(woken.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            Pin::new(&mut future).poll(&mut cx2),
+            Poll::Ready(Err(SendError(4)))
+        );
+    }
+
+    #[test]
+    fn dropping_send_async_keeps_slot_reserved_by_caller() {
+        let (mut tx, rx) = sync_channel(1);
+        let tx2 = tx.clone();
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(tx.poll_ready(&mut cx), Poll::Ready(()));
+        mem::drop(tx.send_async(1));
+        assert_eq!(tx2.try_send(2), Err(TrySendError::Full(2)));
+        assert_eq!(tx.try_send(3), Ok(()));
+        assert_eq!(rx.try_recv(), Ok(3));
+
+        // A slot reserved by the future itself is released when it is dropped.
+        assert_eq!(tx.try_send(4), Ok(()));
+        let mut future = tx.send_async(5);
+        assert!(Pin::new(&mut future).poll(&mut cx).is_pending());
+        mem::drop(future);
+        assert_eq!(rx.try_recv(), Ok(4));
+        assert_eq!(tx2.try_send(6), Ok(()));
+    }
+
+    #[test]
+    fn waker_may_drop_waiting_sender() {
+        struct DroppingWaker(Mutex<Option<SyncSender<usize>>>);
+        impl Wake for DroppingWaker {
+            fn wake(self: Arc<Self>) {
+                mem::drop(self.0.lock().unwrap().take());
+            }
+        }
+
+        let (mut tx, rx) = sync_channel(1);
+        let mut other = tx.clone();
+        tx.try_send(0).unwrap();
+
+        let dropping = Arc::new(DroppingWaker(Mutex::new(None)));
+        let waker = Waker::from(Arc::clone(&dropping));
+        assert!(tx.poll_ready(&mut Context::from_waker(&waker)).is_pending());
+        assert!(other
+            .poll_ready(&mut Context::from_waker(Waker::noop()))
+            .is_pending());
+        *dropping.0.lock().unwrap() = Some(other);
+
+        // Waking `tx` drops `other`, which cancels its waiting
+        assert_eq!(rx.try_recv(), Ok(0));
+        assert!(dropping.0.lock().unwrap().is_none());
+        assert_eq!(rx.len(), 1);
+        assert_eq!(
+            tx.poll_ready(&mut Context::from_waker(Waker::noop())),
+            Poll::Ready(())
+        );
+        assert_eq!(tx.try_send(1), Ok(()));
+        assert_eq!(rx.try_recv(), Ok(1));
+    }
+
+    #[test]
+    fn dropped_send_async_frees_slot() {
+        let (mut tx, rx) = sync_channel(1);
+        let other = tx.clone();
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(tx.try_send(0), Ok(()));
+
+        assert!(Pin::new(&mut tx.send_async(1)).poll(&mut cx).is_pending());
+        assert_eq!(rx.try_recv(), Ok(0));
+        assert_eq!(rx.len(), 0);
+        assert_eq!(other.try_send(2), Ok(()));
+        assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+        assert_eq!(rx.try_recv(), Ok(2));
+
+        // A slot reserved by `poll_ready` is used by `try_send`
+        assert_eq!(tx.poll_ready(&mut cx), Poll::Ready(()));
+        assert_eq!(other.try_send(4), Err(TrySendError::Full(4)));
+        assert_eq!(tx.try_send(5), Ok(()));
+        assert_eq!(rx.len(), 1);
+        assert_eq!(rx.try_recv(), Ok(5));
+
+        // A granted but unobserved slot is passed to the next waiter
+        assert_eq!(tx.try_send(6), Ok(()));
+        let mut future = tx.send_async(7);
+        assert!(Pin::new(&mut future).poll(&mut cx).is_pending());
+        assert_eq!(rx.try_recv(), Ok(6));
+        mem::drop(future);
+        assert_eq!(rx.len(), 0);
+        assert_eq!(other.try_send(8), Ok(()));
+    }
+
+    #[test]
+    fn waiting_senders_are_served_in_order() {
+        let (tx, rx) = sync_channel(1);
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(tx.try_send(0), Ok(()));
+
+        let mut tx1 = tx.clone();
+        let mut tx2 = tx.clone();
+        assert!(tx1.poll_ready(&mut cx).is_pending());
+        assert!(tx2.poll_ready(&mut cx).is_pending());
+
+        assert_eq!(rx.try_recv(), Ok(0));
+        assert!(tx2.poll_ready(&mut cx).is_pending());
+        assert_eq!(tx.try_send(1), Err(TrySendError::Full(1)));
+        assert_eq!(
+            Pin::new(&mut tx1.send_async(1)).poll(&mut cx),
+            Poll::Ready(Ok(()))
+        );
+
+        // A canceled waiter passes its turn to the next one.
+        let mut tx3 = tx.clone();
+        assert!(tx3.poll_ready(&mut cx).is_pending());
+        mem::drop(tx2);
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert!(tx3.poll_ready(&mut cx).is_ready());
+    }
+
+    #[test]
+    fn send_async_works_with_many_producers() {
+        let (tx, rx) = sync_channel(2);
+        let handles = (0..4)
+            .map(|i| {
+                let mut tx = tx.clone();
+                thread::spawn(move || {
+                    let waker = park::thread_waker();
+                    let mut cx = Context::from_waker(&waker);
+                    for j in 0..50 {
+                        let mut future = tx.send_async(i * 50 + j);
+                        while Pin::new(&mut future).poll(&mut cx).is_pending() {
+                            park::park_until(None);
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        mem::drop(tx);
+
+        let mut values = Vec::new();
+        while let Ok(v) = rx.recv() {
+            values.push(v);
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        values.sort();
+        assert_eq!(values, (0..200).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn blocking_send_works() {
+        let (tx, rx) = sync_channel(2);
+        let handles = (0..4)
+            .map(|i| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for j in 0..50 {
+                        tx.send(i * 50 + j).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        mem::drop(tx);
+
+        let mut values = Vec::new();
+        while let Ok(v) = rx.recv() {
+            values.push(v);
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        values.sort();
+        assert_eq!(values, (0..200).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn send_timeout_works() {
+        let (tx, rx) = sync_channel(1);
+        assert_eq!(tx.send_timeout(1, Duration::from_millis(1)), Ok(()));
+        assert_eq!(
+            tx.send_timeout(2, Duration::from_millis(1)),
+            Err(SendTimeoutError::Timeout(2))
+        );
+
+        // The timed out waiter does not hold on to the freed slot.
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(tx.try_send(2), Ok(()));
+
+        let handle = thread::spawn(move || tx.send_timeout(3, Duration::from_secs(60)));
+        thread::sleep(Duration::from_millis(10));
+        mem::drop(rx);
+        assert_eq!(
+            handle.join().unwrap(),
+            Err(SendTimeoutError::Disconnected(3))
+        );
+    }
+
+    #[test]
+    fn blocking_send_uses_reserved_slot() {
+        let (mut tx, rx) = sync_channel(1);
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(tx.poll_ready(&mut cx), Poll::Ready(()));
+        assert_eq!(tx.send_timeout(1, Duration::from_millis(1)), Ok(()));
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.len(), 0);
+
+        assert_eq!(tx.poll_ready(&mut cx), Poll::Ready(()));
+        tx.extend(vec![2]);
+        assert_eq!(rx.try_recv(), Ok(2));
+    }
+
+    #[test]
+    fn zero_bound_fails_fast() {
+        let (mut tx, rx) = sync_channel(0);
+        assert_eq!(tx.send(1), Err(SendError(1)));
+        assert_eq!(
+            tx.send_timeout(2, Duration::from_secs(60)),
+            Err(SendTimeoutError::Timeout(2))
+        );
+
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(tx.poll_ready(&mut cx), Poll::Ready(()));
+        assert_eq!(
+            pin!(tx.send_async(3)).poll(&mut cx),
+            Poll::Ready(Err(SendError(3)))
+        );
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(rx.len(), 0);
+    }
+
+    #[test]
+    fn iterators_work() {
+        let (mut tx, rx) = sync_channel(2);
+        tx.extend(vec![1, 2]);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(rx.try_iter().next(), None);
+
+        let handle = thread::spawn(move || tx.extend(3..10));
+        assert_eq!(rx.iter().take(3).collect::<Vec<_>>(), [3, 4, 5]);
+        assert_eq!(rx.into_iter().collect::<Vec<_>>(), [6, 7, 8, 9]);
+        handle.join().unwrap();
+
+        let (mut tx, rx) = channel();
+        tx.extend(0..3);
+        mem::drop(tx);
+        let mut values = Vec::new();
+        for v in &rx {
+            values.push(v);
+        }
+        assert_eq!(values, [0, 1, 2]);
+    }
+
+    #[test]
+    fn batched_recv_frees_slots() {
+        let (tx, rx) = sync_channel(3);
+        for i in 0..3 {
+            tx.try_send(i).unwrap();
+        }
+
+        let mut buf = Vec::new();
+        assert_eq!(rx.try_recv_many(&mut buf, 2), 2);
+        assert_eq!(buf, [0, 1]);
+        assert_eq!(tx.try_send(3), Ok(()));
+        assert_eq!(tx.try_send(4), Ok(()));
+        assert_eq!(tx.try_send(5), Err(TrySendError::Full(5)));
+
+        // The slots are freed once the iterator is exhausted or dropped
+        let mut drain = rx.drain();
+        assert_eq!(drain.next(), Some(2));
+        assert_eq!(drain.next(), Some(3));
+        assert_eq!(tx.try_send(5), Err(TrySendError::Full(5)));
+        mem::drop(drain);
+        assert_eq!(tx.try_send(5), Ok(()));
+        assert_eq!(rx.drain().collect::<Vec<_>>(), [4, 5]);
+        assert_eq!(rx.try_recv_many(&mut buf, 0), 0);
+        assert_eq!(tx.try_send_batch(6..9), Ok(()));
+    }
+
+    #[test]
+    fn send_batch_works() {
+        let (tx, rx) = channel();
+        assert_eq!(tx.send(0), Ok(()));
+        assert_eq!(tx.send_batch(1..4), Ok(()));
+        assert_eq!(tx.send_batch(None), Ok(()));
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 1, 2, 3]);
+
+        mem::drop(rx);
+        assert_eq!(tx.send_batch(vec![4, 5]), Err(SendError(vec![4, 5])));
+        assert_eq!(tx.send_batch(vec![]), Err(SendError(vec![])));
+
+        let (tx, rx) = sync_channel(3);
+        assert_eq!(tx.try_send_batch(0..2), Ok(()));
+        assert_eq!(tx.try_send_batch(2..4), Err(TrySendError::Full(vec![2, 3])));
+        assert_eq!(tx.try_send_batch(2..3), Ok(()));
+        assert_eq!(rx.try_recv(), Ok(0));
+        assert_eq!(
+            tx.try_send_batch(0..4),
+            Err(TrySendError::Full(vec![0, 1, 2, 3]))
+        );
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 2]);
+
+        mem::drop(rx);
+        assert_eq!(
+            tx.try_send_batch(vec![4]),
+            Err(TrySendError::Disconnected(vec![4]))
+        );
+        assert_eq!(
+            tx.try_send_batch(vec![]),
+            Err(TrySendError::Disconnected(vec![]))
+        );
+    }
+
+    #[test]
+    fn len_works() {
+        let (tx, rx) = channel();
+        assert_eq!(rx.len(), 0);
+        tx.send(1).unwrap();
+        tx.send_batch(vec![2, 3]).unwrap();
+        assert_eq!(rx.len(), 3);
+        assert_eq!(rx.try_iter().count(), 3);
+        assert!(rx.is_empty());
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(rx.len(), 0);
+
+        let (tx, rx) = sync_channel(3);
+        assert_eq!((tx.capacity(), tx.remaining()), (3, 3));
+        tx.try_send(1).unwrap();
+        tx.try_send_batch(vec![2, 3]).unwrap();
+        assert_eq!((tx.len(), tx.remaining()), (3, 0));
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!((rx.len(), tx.remaining()), (2, 1));
+
+        mem::drop(rx);
+        assert_eq!(tx.try_send(4), Err(TrySendError::Disconnected(4)));
+        assert_eq!((tx.len(), tx.remaining()), (0, 0));
+
+        // Closing keeps the pending values countable until they are received.
+        let (tx, rx) = sync_channel(3);
+        tx.try_send(1).unwrap();
+        rx.close();
+        assert_eq!((tx.len(), tx.remaining()), (1, 0));
+        mem::drop(rx);
+        assert_eq!(tx.len(), 0);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn canceled_send_with_is_counted_as_failed() {
+        let (tx, rx) = channel::<usize>();
+        mem::drop(rx);
+        assert_eq!(
+            tx.send_with(|| panic!("never called")),
+            Err(SendWithError::Canceled)
+        );
+        assert_eq!(tx.stats().failed_disconnected, 1);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn stats_works() {
+        let (tx, rx) = channel();
+        tx.send_batch(0..3).unwrap();
+        let mut buf = Vec::new();
+        assert_eq!(rx.try_recv_many(&mut buf, 2), 2);
+        tx.send(3).unwrap();
+
+        let stats = rx.stats();
+        assert_eq!((stats.sent, stats.received), (4, 2));
+        assert_eq!((stats.depth, stats.peak_depth), (2, 3));
+
+        let (tx, rx) = sync_channel(2);
+        tx.try_send_batch(0..2).unwrap();
+        assert!(tx.try_send_batch(2..3).is_err());
+        assert!(tx.try_send(2).is_err());
+        assert_eq!(rx.drain().count(), 2);
+
+        let stats = rx.stats();
+        assert_eq!((stats.sent, stats.received, stats.rejected_full), (2, 2, 2));
+        assert_eq!((stats.depth, stats.peak_depth), (0, 2));
+        assert_eq!(stats.failed_disconnected, 0);
+
+        let tx2 = tx.clone();
+        mem::drop(rx);
+        assert!(tx.try_send(3).is_err());
+        assert!(tx2.send(4).is_err());
+        assert_eq!(tx.stats().failed_disconnected, 2);
+        assert_eq!(tx2.stats(), tx.stats());
+
+        let (tx, rx) = channel();
+        tx.send(0).unwrap();
+        mem::drop(rx);
+        assert!(tx.send_batch(1..3).is_err());
+        let stats = tx.stats();
+        assert_eq!((stats.sent, stats.failed_disconnected), (1, 1));
+    }
+
+    #[test]
+    fn weak_sender_works() {
+        let (tx, rx) = channel();
+        let weak = tx.downgrade();
+        assert_eq!(rx.sender_count(), 1);
+
+        let tx2 = weak.upgrade().unwrap();
+        assert_eq!(rx.sender_count(), 2);
+        tx2.send(1).unwrap();
+        mem::drop(tx);
+        mem::drop(tx2);
+
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Err(RecvError));
+        assert!(weak.clone().upgrade().is_none());
+        assert_eq!(rx.sender_count(), 0);
+    }
+
+    #[test]
+    fn close_works() {
+        let (tx, rx) = sync_channel(2);
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+
+        let blocked = {
+            let tx = tx.clone();
+            thread::spawn(move || tx.send(3))
+        };
+        thread::sleep(Duration::from_millis(10));
+        rx.close();
+        assert_eq!(blocked.join().unwrap(), Err(SendError(3)));
+        assert_eq!(tx.try_send(4), Err(TrySendError::Disconnected(4)));
+        assert_eq!(
+            tx.try_send_batch(vec![5]),
+            Err(TrySendError::Disconnected(vec![5]))
+        );
+
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(tx.try_send(6), Err(TrySendError::Disconnected(6)));
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(rx.recv(), Err(RecvError));
+        assert!(tx.is_disconnected());
+    }
 }
@@ -26,11 +26,44 @@
 //!     }
 //!  }
 //! ```
+//!
+//! If the receiving thread has nothing else to do, it can block instead of spinning:
+//!
+//! ```
+//! use std::thread;
+//! use nbchan::oneshot;
+//!
+//! let (tx, mut rx) = oneshot::channel();
+//! thread::spawn(|| { tx.send(10).unwrap(); });
+//! assert_eq!(rx.recv(), Ok(10));
+//! ```
 #[doc(no_inline)]
-pub use std::sync::mpsc::{SendError, TryRecvError};
+pub use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError, TryRecvError, TrySendError};
 
+pub use error::SendWithError;
+
+use std::cell::{Cell, UnsafeCell};
+use std::fmt;
+use std::future::Future;
+use std::mem::MaybeUninit;
+use std::pin::Pin;
 use std::ptr;
-use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "metrics")]
+use metrics::ChannelStats;
+use park;
+
+pub use self::promise::{promise, Promise, PromiseError, PromiseReceiver};
+pub use self::set::Set;
+
+pub mod pinned;
+
+mod promise;
+mod set;
 
 /// Creates a new asynchronous oneshot channel, returning the sender/receiver halves.
 ///
@@ -69,6 +102,31 @@ pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
     (tx, rx)
 }
 
+/// Creates a new asynchronous oneshot channel whose shared cell is taken from `pool`.
+///
+/// The returned halves behave exactly like the ones created by [`channel`],
+/// but the cell goes back to `pool` instead of being freed when both of them have finished.
+///
+/// # Examples
+///
+/// ```
+/// use nbchan::oneshot::{self, Pool};
+///
+/// let pool = Pool::new();
+/// for i in 0..10 {
+///     // Except the first iteration, the channel reuses the cell recycled by the previous one.
+///     let (tx, mut rx) = oneshot::channel_in(&pool);
+///     tx.send(i).unwrap();
+///     assert_eq!(rx.try_recv(), Ok(i));
+/// }
+/// ```
+pub fn channel_in<T>(pool: &Pool<T>) -> (Sender<T>, Receiver<T>) {
+    let (shared0, shared1) = SharedBox::allocate_in(pool);
+    let tx = Sender(shared0);
+    let rx = Receiver(shared1);
+    (tx, rx)
+}
+
 /// The sending-half of an asynchronous oneshot channel.
 #[derive(Debug)]
 pub struct Sender<T>(SharedBox<T>);
@@ -92,36 +150,123 @@ impl<T> Sender<T> {
     /// assert_eq!(tx.send(1).unwrap_err().0, 1);
     /// ```
     pub fn send(mut self, t: T) -> Result<(), SendError<T>> {
-        let new = into_raw_ptr(t);
-        let old = self.0.swap(new);
-        if old == mark_empty() {
-            // Secceeded.
-            self.0.abandon();
+        self.0.store_value(t);
+        if self.0.complete_tx(VALUE) {
             Ok(())
         } else {
             // Failed; the receiver already has dropped.
-            debug_assert_eq!(old, mark_dropped());
-            let t = from_raw_ptr(self.0.load());
-            self.0.release();
+            let t = self.0.take_value();
+            self.0.finish(TX_CLOSED | TX_DONE | TX_FAILED, RX_DONE);
             Err(SendError(t))
         }
     }
+
+    /// Sends a value built by `f`, unless the receiver already has dropped.
+    ///
+    /// `f` is called only if the channel is still alive at the time of the call.
+    /// If it is not, this returns `SendWithError::Canceled` without calling `f`.
+    ///
+    /// This method will never block the current thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nbchan::oneshot::{channel, SendWithError};
+    ///
+    /// let (tx, mut rx) = channel();
+    /// tx.send_with(|| 1).unwrap();
+    /// assert_eq!(rx.try_recv(), Ok(1));
+    ///
+    /// let (tx, rx) = channel::<usize>();
+    /// drop(rx);
+    /// assert_eq!(tx.send_with(|| unreachable!()), Err(SendWithError::Canceled));
+    /// ```
+    pub fn send_with<F>(mut self, f: F) -> Result<(), SendWithError<T>>
+    where
+        F: FnOnce() -> T,
+    {
+        if self.is_canceled() {
+            // Counted as a failed sending, like the one of `mpsc::Sender::send_with`.
+            self.0.finish(TX_CLOSED | TX_DONE | TX_FAILED, RX_DONE);
+            Err(SendWithError::Canceled)
+        } else {
+            self.send(f())
+                .map_err(|SendError(t)| SendWithError::Disconnected(t))
+        }
+    }
+
+    /// Returns `true` if the receiver has dropped (or closed), otherwise `false`.
+    ///
+    /// If this returns `true`, any subsequent `send` is guaranteed to fail.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nbchan::oneshot::channel;
+    ///
+    /// let (tx, rx) = channel::<()>();
+    /// assert!(!tx.is_canceled());
+    ///
+    /// drop(rx);
+    /// assert!(tx.is_canceled());
+    /// ```
+    pub fn is_canceled(&self) -> bool {
+        self.0.load() & RX_CLOSED != 0
+    }
+
+    /// Blocks the current thread until the receiver has dropped.
+    pub fn wait_canceled(&mut self) {
+        let waker = park::thread_waker();
+        while self.0.register_tx_task(&waker) {
+            park::park_until(None);
+        }
+    }
+
+    /// Polls whether the receiver has dropped.
+    ///
+    /// If the receiver is still alive, the waker of `cx` will be woken when it drops.
+    pub fn poll_canceled(&mut self, cx: &mut Context) -> Poll<()> {
+        if self.0.register_tx_task(cx.waker()) {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+
+    /// Returns a future that completes when the receiver has dropped.
+    ///
+    /// This is useful for abandoning expensive computations whose result is no longer needed.
+    pub fn closed(&mut self) -> Closed<'_, T> {
+        Closed(self)
+    }
 }
 impl<T> Drop for Sender<T> {
     fn drop(&mut self) {
-        if self.0.is_available() {
-            // This channel has not been used.
-            let old = self.0.swap(mark_dropped());
-            if old == mark_dropped() {
-                // The peer (i.e., receiver) dropped first.
-                self.0.release();
-            }
+        if self.0.is_available() && !self.0.complete_tx(0) {
+            // The peer (i.e., receiver) dropped first.
+            self.0.finish(TX_CLOSED | TX_DONE, RX_DONE);
         }
     }
 }
 unsafe impl<T: Send> Send for Sender<T> {}
 
+/// A future which completes when the receiver of a oneshot channel has dropped.
+///
+/// This is created by the [`Sender::closed`] method.
+#[derive(Debug)]
+pub struct Closed<'a, T: 'a>(&'a mut Sender<T>);
+impl<'a, T> Future for Closed<'a, T> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.get_mut().0.poll_canceled(cx)
+    }
+}
+
 /// The receiving-half of an asynchronous oneshot channel.
+///
+/// `Receiver` also implements `Future`, which resolves to the sent value
+/// (or `RecvError` if the sender has dropped without sending).
 #[derive(Debug)]
 pub struct Receiver<T>(SharedBox<T>);
 impl<T> Receiver<T> {
@@ -131,120 +276,1002 @@ impl<T> Receiver<T> {
             return Err(TryRecvError::Disconnected);
         }
 
-        let ptr = self.0.load();
-        if ptr == mark_empty() {
-            Err(TryRecvError::Empty)
-        } else if ptr == mark_dropped() {
-            self.0.release();
+        let state = self.0.load();
+        if state & VALUE != 0 {
+            let t = self.0.take_value();
+            self.0.complete_rx(VALUE);
+            self.0.mark_received();
+            Ok(t)
+        } else if state & RX_CLOSED != 0 {
+            // Keeps the cell so that `state()` can tell that this receiver has closed.
+            Err(TryRecvError::Disconnected)
+        } else if state & TX_CLOSED != 0 {
+            self.0.complete_rx(0);
             Err(TryRecvError::Disconnected)
         } else {
-            let t = from_raw_ptr(ptr);
-            self.0.release();
-            Ok(t)
+            Err(TryRecvError::Empty)
         }
     }
-}
-impl<T> Drop for Receiver<T> {
-    fn drop(&mut self) {
-        if self.0.is_available() {
-            let old = self.0.swap(mark_dropped());
-            if old != mark_empty() {
-                // The peer (i.e., sender) dropped first.
-                if old != mark_dropped() {
-                    // The channel has an unreceived item.
-                    let _t = from_raw_ptr(old);
-                }
-                self.0.release();
+
+    /// Returns the current state of this channel, without receiving the value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nbchan::oneshot::{channel, ReceiverState};
+    ///
+    /// let (tx, mut rx) = channel();
+    /// assert_eq!(rx.state(), ReceiverState::Empty);
+    ///
+    /// tx.send(1).unwrap();
+    /// assert_eq!(rx.state(), ReceiverState::Ready);
+    ///
+    /// rx.try_recv().unwrap();
+    /// assert_eq!(rx.state(), ReceiverState::AlreadyReceived);
+    ///
+    /// let (tx, rx) = channel::<()>();
+    /// drop(tx);
+    /// assert_eq!(rx.state(), ReceiverState::SenderDropped);
+    /// ```
+    pub fn state(&self) -> ReceiverState {
+        if self.0.is_received() {
+            return ReceiverState::AlreadyReceived;
+        }
+        if !self.0.is_available() {
+            return ReceiverState::SenderDropped;
+        }
+
+        let state = self.0.load();
+        if state & VALUE != 0 {
+            ReceiverState::Ready
+        } else if state & RX_CLOSED != 0 {
+            // A send refused after the close also sets `TX_CLOSED`.
+            ReceiverState::Closed
+        } else if state & TX_CLOSED != 0 {
+            ReceiverState::SenderDropped
+        } else {
+            ReceiverState::Empty
+        }
+    }
+
+    /// Returns a snapshot of the counters of this channel.
+    ///
+    /// As a oneshot channel carries at most one value, the counters are not recorded
+    /// separately but derived from the [`state`](#method.state) of the channel.
+    /// The state only moves forward, so the counters never decrease.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nbchan::oneshot::channel;
+    ///
+    /// let (tx, mut rx) = channel();
+    /// rx.close();
+    /// assert!(tx.send(1).is_err());
+    /// assert_eq!(rx.stats().failed_disconnected, 1);
+    ///
+    /// assert!(rx.try_recv().is_err());
+    /// assert_eq!(rx.stats().failed_disconnected, 1);
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> ChannelStats {
+        let mut stats = ChannelStats::default();
+        match self.state() {
+            ReceiverState::Ready => {
+                stats.sent = 1;
+                stats.depth = 1;
+                stats.peak_depth = 1;
+            }
+            ReceiverState::AlreadyReceived => {
+                stats.sent = 1;
+                stats.received = 1;
+                stats.peak_depth = 1;
             }
+            ReceiverState::Closed if self.0.load() & TX_FAILED != 0 => {
+                stats.failed_disconnected = 1;
+            }
+            _ => {}
         }
+        stats
     }
-}
-unsafe impl<T: Send> Send for Receiver<T> {}
 
-#[derive(Debug, Clone)]
-struct SharedBox<T>(*mut AtomicPtr<T>);
-impl<T> SharedBox<T> {
-    #[inline]
-    pub fn allocate() -> (Self, Self) {
-        let ptr = into_raw_ptr(AtomicPtr::default());
-        (SharedBox(ptr), SharedBox(ptr))
+    /// Returns `true` if a value can be received immediately, otherwise `false`.
+    pub fn is_ready(&self) -> bool {
+        self.state() == ReceiverState::Ready
     }
 
-    #[inline]
-    pub fn release(&mut self) {
-        debug_assert_ne!(self.0, ptr::null_mut());
-        let _ = from_raw_ptr(self.0);
-        self.0 = ptr::null_mut();
+    /// Returns `true` if no value will be received from now on, otherwise `false`.
+    ///
+    /// This is the case if the sender has dropped without sending,
+    /// the receiver has closed, or the value already has been received.
+    pub fn is_disconnected(&self) -> bool {
+        !matches!(self.state(), ReceiverState::Empty | ReceiverState::Ready)
     }
 
-    #[inline]
-    pub fn abandon(&mut self) {
-        debug_assert_ne!(self.0, ptr::null_mut());
-        self.0 = ptr::null_mut();
+    /// Closes the receiving half of the channel without dropping it.
+    ///
+    /// After this call, any subsequent `send` on the sender will fail and return the value back.
+    /// A value which was sent before the call is still available via `try_recv` (or `recv`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nbchan::oneshot::{channel, TryRecvError};
+    ///
+    /// // Late replies are refused
+    /// let (tx, mut rx) = channel();
+    /// rx.close();
+    /// assert!(tx.is_canceled());
+    /// assert_eq!(tx.send(1).unwrap_err().0, 1);
+    /// assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    ///
+    /// // A value that arrived just in time can still be received
+    /// let (tx, mut rx) = channel();
+    /// tx.send(1).unwrap();
+    /// rx.close();
+    /// assert_eq!(rx.try_recv(), Ok(1));
+    /// ```
+    pub fn close(&mut self) {
+        if self.0.is_available() {
+            self.0.close_rx();
+        }
     }
 
-    #[inline]
-    pub fn is_available(&self) -> bool {
-        !self.0.is_null()
+    /// Blocks the current thread until a value is received or the sender has dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::thread;
+    /// use nbchan::oneshot::{channel, RecvError};
+    ///
+    /// let (tx, mut rx) = channel();
+    /// thread::spawn(move || tx.send(1).unwrap());
+    /// assert_eq!(rx.recv(), Ok(1));
+    ///
+    /// let (tx, mut rx) = channel::<()>();
+    /// thread::spawn(move || drop(tx));
+    /// assert_eq!(rx.recv(), Err(RecvError));
+    /// ```
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        self.recv_until(None).map_err(|_| RecvError)
     }
 
-    #[inline]
-    pub fn swap(&self, value: *mut T) -> *mut T {
-        debug_assert_ne!(self.0, ptr::null_mut());
-        unsafe { &*self.0 }.swap(value, Ordering::SeqCst)
+    /// Blocks the current thread until a value is received, the sender has dropped,
+    /// or `timeout` has elapsed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use nbchan::oneshot::{channel, RecvTimeoutError};
+    ///
+    /// let (_tx, mut rx) = channel::<()>();
+    /// assert_eq!(
+    ///     rx.recv_timeout(Duration::from_millis(1)),
+    ///     Err(RecvTimeoutError::Timeout)
+    /// );
+    /// ```
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.recv_until(Some(deadline)),
+            None => self.recv_until(None),
+        }
     }
 
-    #[inline]
-    pub fn load(&self) -> *mut T {
-        unsafe { &*self.0 }.load(Ordering::SeqCst)
+    /// Blocks the current thread until a value is received, the sender has dropped,
+    /// or `deadline` is reached.
+    pub fn recv_deadline(&mut self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        self.recv_until(Some(deadline))
+    }
+
+    fn recv_until(&mut self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        let waker = park::thread_waker();
+        loop {
+            match self.try_recv() {
+                Ok(t) => return Ok(t),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => {}
+            }
+            if self.0.register_rx_task(&waker) && !park::park_until(deadline) {
+                return Err(RecvTimeoutError::Timeout);
+            }
+        }
     }
 }
-unsafe impl<T: Send> Send for SharedBox<T> {}
+impl<T> Future for Receiver<T> {
+    type Output = Result<T, RecvError>;
 
-#[inline]
-fn mark_dropped<T>() -> *mut T {
-    static MARK_DROPPED: &u8 = &0;
-    MARK_DROPPED as *const _ as _
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        loop {
+            match this.try_recv() {
+                Err(TryRecvError::Empty) => {}
+                result => return Poll::Ready(result.map_err(|_| RecvError)),
+            }
+            if this.0.register_rx_task(cx.waker()) {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        if self.0.is_available() {
+            self.0.complete_rx(0);
+        }
+    }
 }
+unsafe impl<T: Send> Send for Receiver<T> {}
 
-#[inline]
-fn mark_empty<T>() -> *mut T {
-    ptr::null_mut()
+/// The state of a oneshot channel, observed from the receiving side.
+///
+/// This is returned by the [`Receiver::state`] method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReceiverState {
+    /// The sender has not sent a value yet.
+    Empty,
+
+    /// A value has been sent and it can be received.
+    Ready,
+
+    /// The sender has dropped without sending a value.
+    SenderDropped,
+
+    /// The receiver has closed before a value was sent.
+    ///
+    /// This is kept even after the sender has tried to send a value (and failed) or dropped.
+    Closed,
+
+    /// The value already has been received.
+    AlreadyReceived,
 }
 
-#[inline]
-fn into_raw_ptr<T>(t: T) -> *mut T {
-    Box::into_raw(Box::new(t))
+/// Creates a new reusable oneshot slot, returning the sender/receiver halves.
+///
+/// A slot is a oneshot channel which can carry a new value once the previous one has been taken.
+/// Like the ordinary channel, it shares a single cell between the halves,
+/// so no allocation happens after the creation.
+///
+/// # Examples
+///
+/// ```
+/// use nbchan::oneshot::{self, TryRecvError, TrySendError};
+///
+/// let (mut tx, mut rx) = oneshot::slot();
+/// for i in 0..3 {
+///     tx.try_send(i).unwrap();
+///
+///     // The slot still holds the previous value
+///     assert_eq!(tx.try_send(10), Err(TrySendError::Full(10)));
+///
+///     assert_eq!(rx.try_recv(), Ok(i));
+///     assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+/// }
+///
+/// drop(tx);
+/// assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+/// ```
+pub fn slot<T>() -> (SlotSender<T>, SlotReceiver<T>) {
+    let (shared0, shared1) = SharedBox::allocate();
+    let tx = SlotSender(shared0);
+    let rx = SlotReceiver(shared1);
+    (tx, rx)
 }
 
-#[inline]
-fn from_raw_ptr<T>(ptr: *mut T) -> T {
-    unsafe { *Box::from_raw(ptr) }
+/// The sending-half of a reusable oneshot slot.
+#[derive(Debug)]
+pub struct SlotSender<T>(SharedBox<T>);
+impl<T> SlotSender<T> {
+    /// Attempts to put a value into the slot.
+    ///
+    /// If the previous value has not been taken by the receiver yet,
+    /// this will fail with `TrySendError::Full`.
+    /// If the receiver has dropped, this fails with `TrySendError::Disconnected`
+    /// even if the slot still holds a value.
+    ///
+    /// This method will never block the current thread.
+    pub fn try_send(&mut self, t: T) -> Result<(), TrySendError<T>> {
+        self.0.put_value(t)
+    }
+
+    /// Returns `true` if the receiver has dropped, otherwise `false`.
+    pub fn is_canceled(&self) -> bool {
+        self.0.load() & RX_CLOSED != 0
+    }
+}
+impl<T> Drop for SlotSender<T> {
+    fn drop(&mut self) {
+        if !self.0.complete_tx(0) {
+            // The peer (i.e., receiver) dropped first.
+            self.0.finish(TX_CLOSED | TX_DONE, RX_DONE);
+        }
+    }
 }
+unsafe impl<T: Send> Send for SlotSender<T> {}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+/// The receiving-half of a reusable oneshot slot.
+#[derive(Debug)]
+pub struct SlotReceiver<T>(SharedBox<T>);
+impl<T> SlotReceiver<T> {
+    /// Attempts to take the value in the slot without blocking.
+    ///
+    /// After a successful call, the sender can put the next value into the slot.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        if !self.0.is_available() {
+            return Err(TryRecvError::Disconnected);
+        }
 
-    #[test]
-    fn send_and_recv_succeeds() {
-        let (tx, mut rx) = channel();
-        tx.send(1).unwrap();
-        assert_eq!(rx.try_recv(), Ok(1));
+        let state = self.0.load();
+        if state & VALUE != 0 {
+            Ok(self.0.pop_value())
+        } else if state & TX_CLOSED != 0 {
+            self.0.complete_rx(0);
+            Err(TryRecvError::Disconnected)
+        } else {
+            Err(TryRecvError::Empty)
+        }
     }
 
-    #[test]
-    fn send_succeeds() {
-        let (tx, rx) = channel();
-        tx.send(1).unwrap();
-        drop(rx);
+    /// Polls the slot for a value.
+    ///
+    /// If the slot is empty, the waker of `cx` will be woken when a value is put or the sender drops.
+    pub fn poll_recv(&mut self, cx: &mut Context) -> Poll<Result<T, RecvError>> {
+        loop {
+            match self.try_recv() {
+                Err(TryRecvError::Empty) => {}
+                result => return Poll::Ready(result.map_err(|_| RecvError)),
+            }
+            if self.0.register_rx_task(cx.waker()) {
+                return Poll::Pending;
+            }
+        }
     }
 
-    #[test]
-    fn send_fails() {
-        let (tx, rx) = channel();
-        drop(rx);
-        assert_eq!(tx.send(1), Err(SendError(1)));
+    /// Blocks the current thread until a value is put into the slot or the sender has dropped.
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        let waker = park::thread_waker();
+        loop {
+            match self.try_recv() {
+                Err(TryRecvError::Empty) => {}
+                result => return result.map_err(|_| RecvError),
+            }
+            if self.0.register_rx_task(&waker) {
+                park::park_until(None);
+            }
+        }
+    }
+}
+impl<T> Drop for SlotReceiver<T> {
+    fn drop(&mut self) {
+        if self.0.is_available() {
+            self.0.complete_rx(0);
+        }
+    }
+}
+unsafe impl<T: Send> Send for SlotReceiver<T> {}
+
+/// Creates a new oneshot channel whose value can be observed by many receivers.
+///
+/// The receiving half can be cloned, and every clone receives a clone of the single sent value.
+/// Both sending and receiving will not block the calling thread.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use nbchan::oneshot::{self, TryRecvError};
+///
+/// let (tx, rx) = oneshot::shared_channel();
+/// let handles = (0..3)
+///     .map(|_| {
+///         let rx = rx.clone();
+///         thread::spawn(move || loop {
+///             match rx.try_recv() {
+///                 Err(TryRecvError::Empty) => thread::yield_now(),
+///                 result => return result,
+///             }
+///         })
+///     })
+///     .collect::<Vec<_>>();
+///
+/// tx.send("config".to_owned()).unwrap();
+/// for handle in handles {
+///     assert_eq!(handle.join().unwrap(), Ok("config".to_owned()));
+/// }
+/// ```
+pub fn shared_channel<T: Clone>() -> (SharedSender<T>, SharedReceiver<T>) {
+    let shared = Arc::new(SharedValue {
+        state: AtomicUsize::new(SHARED_EMPTY),
+        value: UnsafeCell::new(MaybeUninit::uninit()),
+    });
+    let tx = SharedSender(Some(Arc::clone(&shared)));
+    let rx = SharedReceiver(shared);
+    (tx, rx)
+}
+
+/// The sending-half of a oneshot channel created by [`shared_channel`].
+#[derive(Debug)]
+pub struct SharedSender<T>(Option<Arc<SharedValue<T>>>);
+impl<T> SharedSender<T> {
+    /// Attempts to send a value on this channel, returning it back if it could not be sent.
+    ///
+    /// The send fails only if all of the receivers have dropped.
+    ///
+    /// This method will never block the current thread.
+    pub fn send(mut self, t: T) -> Result<(), SendError<T>> {
+        let shared = self.0.take().expect("Never fails");
+        if Arc::strong_count(&shared) == 1 {
+            return Err(SendError(t));
+        }
+        unsafe { (*shared.value.get()).as_mut_ptr().write(t) };
+        shared.state.store(SHARED_SENT, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns `true` if all of the receivers have dropped, otherwise `false`.
+    #[allow(clippy::unnecessary_map_or)] // `Option::is_none_or` requires Rust 1.82
+    pub fn is_canceled(&self) -> bool {
+        self.0.as_ref().map_or(true, |s| Arc::strong_count(s) == 1)
+    }
+}
+impl<T> Drop for SharedSender<T> {
+    fn drop(&mut self) {
+        if let Some(shared) = self.0.take() {
+            shared.state.store(SHARED_DROPPED, Ordering::SeqCst);
+        }
+    }
+}
+
+/// The receiving-half of a oneshot channel created by [`shared_channel`].
+///
+/// This can be cloned, and each clone observes the same value.
+#[derive(Debug)]
+pub struct SharedReceiver<T>(Arc<SharedValue<T>>);
+impl<T: Clone> SharedReceiver<T> {
+    /// Attempts to return a clone of the sent value without blocking.
+    ///
+    /// Unlike `Receiver::try_recv`, this can be called repeatedly
+    /// and returns the same value every time once it has been sent.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        match self.0.state.load(Ordering::SeqCst) {
+            SHARED_EMPTY => Err(TryRecvError::Empty),
+            SHARED_SENT => Ok(unsafe { (*(*self.0.value.get()).as_ptr()).clone() }),
+            _ => Err(TryRecvError::Disconnected),
+        }
+    }
+}
+impl<T> Clone for SharedReceiver<T> {
+    fn clone(&self) -> Self {
+        SharedReceiver(Arc::clone(&self.0))
+    }
+}
+
+const SHARED_EMPTY: usize = 0;
+const SHARED_SENT: usize = 1;
+const SHARED_DROPPED: usize = 2;
+
+#[derive(Debug)]
+struct SharedValue<T> {
+    state: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+impl<T> Drop for SharedValue<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == SHARED_SENT {
+            unsafe { ptr::drop_in_place(self.value.get_mut().as_mut_ptr()) };
+        }
+    }
+}
+unsafe impl<T: Send> Send for SharedValue<T> {}
+unsafe impl<T: Send + Sync> Sync for SharedValue<T> {}
+
+/// A pool of recycled shared cells of oneshot channels.
+///
+/// Channels created by [`channel_in`] return their cell to the pool when finished,
+/// so creating a channel does not allocate as long as the pool has a free cell.
+///
+/// A pool is owned by a single thread at a time (it is `Send` but not `Sync`),
+/// while the channels created from it can be used and released on any thread.
+/// Cells released after the pool has dropped are simply freed.
+pub struct Pool<T> {
+    inner: Arc<PoolInner<T>>,
+    free: Cell<*mut Shared<T>>,
+}
+impl<T> Pool<T> {
+    /// Makes a new empty pool.
+    pub fn new() -> Self {
+        Pool {
+            inner: Arc::new(PoolInner {
+                returned: AtomicPtr::default(),
+            }),
+            free: Cell::new(ptr::null_mut()),
+        }
+    }
+
+    fn pop(&self) -> Option<*mut Shared<T>> {
+        let mut cell = self.free.get();
+        if cell.is_null() {
+            // Takes all of the cells that have been returned since the last call.
+            cell = self.inner.returned.swap(ptr::null_mut(), Ordering::SeqCst);
+            if cell.is_null() {
+                return None;
+            }
+        }
+        self.free.set(unsafe { (*cell).next });
+        Some(cell)
+    }
+}
+unsafe impl<T: Send> Send for Pool<T> {}
+impl<T> Default for Pool<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T> Drop for Pool<T> {
+    fn drop(&mut self) {
+        let returned = self.inner.returned.swap(mark_closed(), Ordering::SeqCst);
+        for mut cell in [self.free.get(), returned] {
+            while !cell.is_null() {
+                let next = unsafe { (*cell).next };
+                let _ = from_raw_ptr(cell);
+                cell = next;
+            }
+        }
+    }
+}
+impl<T> fmt::Debug for Pool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Pool {{ .. }}")
+    }
+}
+
+#[derive(Debug)]
+struct PoolInner<T> {
+    // A lock-free stack of the cells returned by channels
+    // (or `mark_closed()` if the pool has dropped).
+    returned: AtomicPtr<Shared<T>>,
+}
+impl<T> PoolInner<T> {
+    /// Returns `cell` to the pool which it was allocated from.
+    ///
+    /// If the pool already has dropped, `cell` is freed.
+    unsafe fn recycle(cell: *mut Shared<T>) {
+        (*cell).reset();
+
+        let pool = match (*cell).storage {
+            Storage::Pool(ref pool) => Arc::as_ptr(pool),
+            _ => unreachable!(),
+        };
+        let returned = &(*pool).returned;
+        let mut head = returned.load(Ordering::SeqCst);
+        loop {
+            if head == mark_closed() {
+                // This may drop the last reference to the pool.
+                let _ = from_raw_ptr(cell);
+                return;
+            }
+            (*cell).next = head;
+            match returned.compare_exchange(head, cell, Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => return,
+                Err(h) => head = h,
+            }
+        }
+    }
+}
+
+/// The shared cell holds a value (i.e., the sender has stored it and the receiver has not taken it).
+const VALUE: usize = 0b000_0001;
+
+/// The sender will never touch the value again (i.e., it has sent a value or dropped).
+const TX_CLOSED: usize = 0b000_0010;
+
+/// The sender will never touch the shared cell again.
+const TX_DONE: usize = 0b000_0100;
+
+/// The sender has registered a waker in `Shared::tx_task`.
+const TX_TASK: usize = 0b000_1000;
+
+/// The receiver will never accept a value (i.e., it has received a value, closed or dropped).
+const RX_CLOSED: usize = 0b001_0000;
+
+/// The receiver will never touch the shared cell again.
+const RX_DONE: usize = 0b010_0000;
+
+/// The receiver has registered a waker in `Shared::rx_task`.
+const RX_TASK: usize = 0b100_0000;
+
+/// The sender of a slot is taking the receiver's waker (the receiver must not touch `Shared::rx_task`).
+const TX_WAKING: usize = 0b1000_0000;
+
+/// The sender tried to send a value after the receiver had closed.
+///
+/// This is recorded only if the `metrics` feature is enabled.
+#[cfg(feature = "metrics")]
+const TX_FAILED: usize = 0b1_0000_0000;
+#[cfg(not(feature = "metrics"))]
+const TX_FAILED: usize = 0;
+
+#[derive(Debug)]
+struct Shared<T> {
+    state: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+    tx_task: UnsafeCell<Option<Waker>>,
+    rx_task: UnsafeCell<Option<Waker>>,
+
+    // Where this cell lives, and the next free cell in the pool (if any).
+    storage: Storage<T>,
+    next: *mut Shared<T>,
+}
+impl<T> Shared<T> {
+    fn new(storage: Storage<T>) -> Self {
+        Shared {
+            state: AtomicUsize::new(0),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+            tx_task: UnsafeCell::new(None),
+            rx_task: UnsafeCell::new(None),
+            storage,
+            next: ptr::null_mut(),
+        }
+    }
+
+    fn reset(&mut self) {
+        if *self.state.get_mut() & VALUE != 0 {
+            unsafe { ptr::drop_in_place(self.value.get_mut().as_mut_ptr()) };
+        }
+        *self.state.get_mut() = 0;
+        *self.tx_task.get_mut() = None;
+        *self.rx_task.get_mut() = None;
+    }
+}
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() & VALUE != 0 {
+            // The channel has an unreceived item.
+            unsafe { ptr::drop_in_place(self.value.get_mut().as_mut_ptr()) };
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Storage<T> {
+    /// The cell is freed when released.
+    Heap,
+
+    /// The cell goes back to the pool when released.
+    Pool(Arc<PoolInner<T>>),
+
+    /// The cell is owned by someone else (e.g., `pinned::Receiver`), so releasing it is no-op.
+    Borrowed,
+}
+
+#[derive(Debug, Clone)]
+struct SharedBox<T>(*mut Shared<T>);
+impl<T> SharedBox<T> {
+    #[inline]
+    pub fn allocate() -> (Self, Self) {
+        let ptr = into_raw_ptr(Shared::new(Storage::Heap));
+        (SharedBox(ptr), SharedBox(ptr))
+    }
+
+    #[inline]
+    pub fn allocate_in(pool: &Pool<T>) -> (Self, Self) {
+        let ptr = pool
+            .pop()
+            .unwrap_or_else(|| into_raw_ptr(Shared::new(Storage::Pool(Arc::clone(&pool.inner)))));
+        (SharedBox(ptr), SharedBox(ptr))
+    }
+
+    #[inline]
+    pub fn release(&mut self) {
+        debug_assert_ne!(self.0, ptr::null_mut());
+        match unsafe { &(*self.0).storage } {
+            Storage::Heap => {
+                let _ = from_raw_ptr(self.0);
+            }
+            Storage::Pool(_) => unsafe { PoolInner::recycle(self.0) },
+            Storage::Borrowed => {}
+        }
+        self.0 = ptr::null_mut();
+    }
+
+    #[inline]
+    pub fn abandon(&mut self) {
+        debug_assert_ne!(self.0, ptr::null_mut());
+        self.0 = ptr::null_mut();
+    }
+
+    #[inline]
+    pub fn is_available(&self) -> bool {
+        !self.0.is_null() && !self.is_received()
+    }
+
+    /// Marks this (already relinquished) reference as the one which has received the value.
+    #[inline]
+    pub fn mark_received(&mut self) {
+        debug_assert_eq!(self.0, ptr::null_mut());
+        self.0 = mark_received();
+    }
+
+    #[inline]
+    pub fn is_received(&self) -> bool {
+        self.0 == mark_received()
+    }
+
+    #[inline]
+    pub fn load(&self) -> usize {
+        self.shared().state.load(Ordering::SeqCst)
+    }
+
+    /// Stores the value to be sent.
+    ///
+    /// Only the sender may call this, and only before setting `VALUE`.
+    #[inline]
+    pub fn store_value(&self, value: T) {
+        unsafe { (*self.shared().value.get()).as_mut_ptr().write(value) };
+    }
+
+    /// Moves the stored value out of the cell.
+    ///
+    /// The caller must be the exclusive owner of the value
+    /// (i.e., the receiver after observing `VALUE`, or the sender that failed to set it),
+    /// and must make sure that `VALUE` is unset afterwards.
+    #[inline]
+    pub fn take_value(&self) -> T {
+        unsafe { (*self.shared().value.get()).as_ptr().read() }
+    }
+
+    /// Sets `VALUE` without closing the sender (i.e., puts a value into a slot).
+    pub fn put_value(&self, value: T) -> Result<(), TrySendError<T>> {
+        let mut state = self.load();
+        if state & RX_CLOSED != 0 {
+            // The previous value (if any) will never be taken.
+            return Err(TrySendError::Disconnected(value));
+        }
+        if state & VALUE != 0 {
+            return Err(TrySendError::Full(value));
+        }
+
+        self.store_value(value);
+        loop {
+            if state & RX_CLOSED != 0 {
+                return Err(TrySendError::Disconnected(self.take_value()));
+            }
+            let mut next = state | VALUE;
+            if state & RX_TASK != 0 {
+                next = (next & !RX_TASK) | TX_WAKING;
+            }
+            match self.compare_exchange(state, next) {
+                Ok(_) => break,
+                Err(s) => state = s,
+            }
+        }
+        if state & RX_TASK != 0 {
+            // The receiver never touches `rx_task` while `TX_WAKING` is set,
+            // so the waker is taken out first and woken after the flag is unset.
+            let waker = unsafe { (*self.shared().rx_task.get()).take() };
+            self.shared().state.fetch_and(!TX_WAKING, Ordering::SeqCst);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes the value out of a slot and unsets `VALUE` so that the sender can put the next one.
+    pub fn pop_value(&self) -> T {
+        let value = self.take_value();
+        self.shared().state.fetch_and(!VALUE, Ordering::SeqCst);
+        value
+    }
+
+    /// Sets `TX_CLOSED` (and `flags`), then relinquishes the sender's reference to the cell.
+    ///
+    /// If the receiver has registered a waker, it will be woken.
+    /// Returns `false` without changing the state if the receiver already has closed;
+    /// in that case the caller is responsible for finishing the sender.
+    pub fn complete_tx(&mut self, flags: usize) -> bool {
+        let mut state = self.load();
+        loop {
+            if state & RX_CLOSED != 0 {
+                return false;
+            }
+            let mut next = state | flags | TX_CLOSED;
+            if state & RX_TASK == 0 {
+                next |= TX_DONE;
+            }
+            match self.compare_exchange(state, next) {
+                Ok(_) => break,
+                Err(s) => state = s,
+            }
+        }
+        if state & RX_TASK != 0 {
+            // The receiver never touches `rx_task` after `TX_CLOSED` is set.
+            self.wake(&self.shared().rx_task);
+            self.finish(TX_DONE, RX_DONE);
+        } else {
+            self.abandon();
+        }
+        true
+    }
+
+    /// Unsets `unset` and sets `RX_CLOSED`, then relinquishes the receiver's reference to the cell.
+    ///
+    /// If the sender is waiting for the cancellation, it will be woken.
+    pub fn complete_rx(&mut self, unset: usize) {
+        let mut state = self.load();
+        loop {
+            let mut next = (state & !unset) | RX_CLOSED;
+            let notify = state & (TX_TASK | TX_CLOSED | RX_CLOSED) == TX_TASK;
+            if !notify {
+                next |= RX_DONE;
+            }
+            match self.compare_exchange(state, next) {
+                Ok(_) if notify => break,
+                Ok(_) if state & TX_DONE != 0 => return self.release(),
+                Ok(_) => return self.abandon(),
+                Err(s) => state = s,
+            }
+        }
+
+        // The sender never touches `tx_task` after `RX_CLOSED` is set.
+        self.wake(&self.shared().tx_task);
+        self.finish(RX_DONE, TX_DONE);
+    }
+
+    /// Sets `RX_CLOSED` while keeping the receiver's reference to the cell.
+    ///
+    /// If the sender is waiting for the cancellation, it will be woken.
+    pub fn close_rx(&self) {
+        let old = self.shared().state.fetch_or(RX_CLOSED, Ordering::SeqCst);
+        if old & (TX_TASK | TX_CLOSED | RX_CLOSED) == TX_TASK {
+            // The sender never touches `tx_task` after `RX_CLOSED` is set.
+            self.wake(&self.shared().tx_task);
+        }
+    }
+
+    /// Registers the waker of the receiving task.
+    ///
+    /// Returns `false` if the cell has a value or the sender already has closed the channel.
+    #[inline]
+    pub fn register_rx_task(&self, waker: &Waker) -> bool {
+        self.register(&self.shared().rx_task, waker, RX_TASK, VALUE | TX_CLOSED)
+    }
+
+    /// Registers the waker of the sending task.
+    ///
+    /// Returns `false` if the receiver already has closed the channel.
+    #[inline]
+    pub fn register_tx_task(&self, waker: &Waker) -> bool {
+        self.register(&self.shared().tx_task, waker, TX_TASK, RX_CLOSED)
+    }
+
+    fn register(
+        &self,
+        task: &UnsafeCell<Option<Waker>>,
+        waker: &Waker,
+        task_flag: usize,
+        peer_closed: usize,
+    ) -> bool {
+        let mut state = self.load();
+        loop {
+            if state & peer_closed != 0 {
+                return false;
+            }
+            if state & TX_WAKING != 0 && task_flag == RX_TASK {
+                // The sender of a slot is taking the waker out of `rx_task`;
+                // lets the caller retry immediately instead of waiting for it.
+                waker.wake_by_ref();
+                return true;
+            }
+            if state & task_flag != 0 {
+                if task_flag == TX_TASK {
+                    // The receiver only reads `tx_task` when waking it,
+                    // so the registered waker can be read concurrently.
+                    let registered = unsafe { &*task.get() };
+                    if registered.as_ref().is_some_and(|w| w.will_wake(waker)) {
+                        return true;
+                    }
+                }
+
+                // The sender of a slot takes the waker out of `rx_task`,
+                // so it must not be touched until the flag is unset here.
+                if let Err(s) = self.compare_exchange(state, state & !task_flag) {
+                    state = s;
+                    continue;
+                }
+                state &= !task_flag;
+            }
+            unsafe { *task.get() = Some(waker.clone()) };
+            match self.compare_exchange(state, state | task_flag) {
+                Ok(_) => return true,
+                Err(s) => state = s,
+            }
+        }
+    }
+
+    #[inline]
+    fn wake(&self, task: &UnsafeCell<Option<Waker>>) {
+        if let Some(waker) = unsafe { &*task.get() } {
+            waker.wake_by_ref();
+        }
+    }
+
+    #[inline]
+    pub fn finish(&mut self, done: usize, peer_done: usize) {
+        let old = self.shared().state.fetch_or(done, Ordering::SeqCst);
+        if old & peer_done != 0 {
+            self.release();
+        } else {
+            self.abandon();
+        }
+    }
+
+    #[inline]
+    fn compare_exchange(&self, current: usize, new: usize) -> Result<usize, usize> {
+        self.shared()
+            .state
+            .compare_exchange(current, new, Ordering::SeqCst, Ordering::SeqCst)
+    }
+
+    #[inline]
+    fn shared(&self) -> &Shared<T> {
+        debug_assert_ne!(self.0, ptr::null_mut());
+        unsafe { &*self.0 }
+    }
+}
+unsafe impl<T: Send> Send for SharedBox<T> {}
+
+#[inline]
+fn mark_closed<T>() -> *mut T {
+    static MARK_CLOSED: &u8 = &0;
+    MARK_CLOSED as *const _ as _
+}
+
+#[inline]
+fn mark_received<T>() -> *mut T {
+    static MARK_RECEIVED: &u8 = &0;
+    MARK_RECEIVED as *const _ as _
+}
+
+#[inline]
+fn into_raw_ptr<T>(t: T) -> *mut T {
+    Box::into_raw(Box::new(t))
+}
+
+#[inline]
+fn from_raw_ptr<T>(ptr: *mut T) -> T {
+    unsafe { *Box::from_raw(ptr) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::mpsc as std_mpsc;
+    use std::sync::Mutex;
+    use std::task::Wake;
+    use std::thread;
+    use test_util::counting_waker;
+
+    #[test]
+    fn send_and_recv_succeeds() {
+        let (tx, mut rx) = channel();
+        tx.send(1).unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+    }
+
+    #[test]
+    fn send_succeeds() {
+        let (tx, rx) = channel();
+        tx.send(1).unwrap();
+        drop(rx);
+    }
+
+    #[test]
+    fn send_fails() {
+        let (tx, rx) = channel();
+        drop(rx);
+        assert_eq!(tx.send(1), Err(SendError(1)));
     }
 
     #[test]
@@ -255,6 +1282,504 @@ mod test {
         assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
     }
 
+    #[test]
+    fn poll_works() {
+        let (tx, mut rx) = channel();
+        let (waker, count) = counting_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert_eq!(Pin::new(&mut rx).poll(&mut cx), Poll::Pending);
+        assert_eq!(Pin::new(&mut rx).poll(&mut cx), Poll::Pending);
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+
+        tx.send(1).unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert_eq!(Pin::new(&mut rx).poll(&mut cx), Poll::Ready(Ok(1)));
+    }
+
+    #[test]
+    fn poll_fails() {
+        let (tx, mut rx) = channel::<()>();
+        let (waker, count) = counting_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert_eq!(Pin::new(&mut rx).poll(&mut cx), Poll::Pending);
+
+        drop(tx);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert_eq!(Pin::new(&mut rx).poll(&mut cx), Poll::Ready(Err(RecvError)));
+    }
+
+    #[test]
+    fn poll_with_another_waker_works() {
+        let (tx, mut rx) = channel();
+        let (waker0, count0) = counting_waker();
+        let (waker1, count1) = counting_waker();
+        assert_eq!(
+            Pin::new(&mut rx).poll(&mut Context::from_waker(&waker0)),
+            Poll::Pending
+        );
+        assert_eq!(
+            Pin::new(&mut rx).poll(&mut Context::from_waker(&waker1)),
+            Poll::Pending
+        );
+
+        tx.send(1).unwrap();
+        assert_eq!(count0.load(Ordering::SeqCst), 0);
+        assert_eq!(count1.load(Ordering::SeqCst), 1);
+        assert_eq!(rx.try_recv(), Ok(1));
+    }
+
+    #[test]
+    fn multithread_poll_works() {
+        for i in 0..100 {
+            let (tx, mut rx) = channel();
+            let (waker, count) = counting_waker();
+            let mut cx = Context::from_waker(&waker);
+            thread::spawn(move || tx.send(i).unwrap());
+            loop {
+                if let Poll::Ready(v) = Pin::new(&mut rx).poll(&mut cx) {
+                    assert_eq!(v, Ok(i));
+                    break;
+                }
+                while count.load(Ordering::SeqCst) == 0 {}
+            }
+        }
+    }
+
+    #[test]
+    fn recv_works() {
+        let (tx, mut rx) = channel();
+        let handle = thread::spawn(move || rx.recv());
+        thread::sleep(Duration::from_millis(10));
+        tx.send(1).unwrap();
+        assert_eq!(handle.join().unwrap(), Ok(1));
+
+        let (tx, mut rx) = channel::<()>();
+        let handle = thread::spawn(move || rx.recv());
+        thread::sleep(Duration::from_millis(10));
+        drop(tx);
+        assert_eq!(handle.join().unwrap(), Err(RecvError));
+    }
+
+    #[test]
+    fn recv_timeout_works() {
+        let (tx, mut rx) = channel();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        assert_eq!(
+            rx.recv_deadline(Instant::now()),
+            Err(RecvTimeoutError::Timeout)
+        );
+
+        thread::spawn(move || tx.send(1).unwrap());
+        assert_eq!(rx.recv_timeout(Duration::from_secs(10)), Ok(1));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn cancellation_works() {
+        let (mut tx, rx) = channel::<()>();
+        assert!(!tx.is_canceled());
+
+        let (waker, count) = counting_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert_eq!(tx.poll_canceled(&mut cx), Poll::Pending);
+        assert_eq!(Pin::new(&mut tx.closed()).poll(&mut cx), Poll::Pending);
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+
+        drop(rx);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(tx.is_canceled());
+        assert_eq!(tx.poll_canceled(&mut cx), Poll::Ready(()));
+        assert_eq!(tx.send(()), Err(SendError(())));
+    }
+
+    #[test]
+    fn wait_canceled_works() {
+        let (mut tx, rx) = channel::<()>();
+        let handle = thread::spawn(move || {
+            tx.wait_canceled();
+            tx.is_canceled()
+        });
+        thread::sleep(Duration::from_millis(10));
+        drop(rx);
+        assert!(handle.join().unwrap());
+    }
+
+    #[test]
+    fn both_sides_waiting_works() {
+        let (mut tx, mut rx) = channel();
+        let (tx_waker, tx_count) = counting_waker();
+        let (rx_waker, rx_count) = counting_waker();
+        assert_eq!(
+            tx.poll_canceled(&mut Context::from_waker(&tx_waker)),
+            Poll::Pending
+        );
+        assert_eq!(
+            Pin::new(&mut rx).poll(&mut Context::from_waker(&rx_waker)),
+            Poll::Pending
+        );
+
+        tx.send(1).unwrap();
+        assert_eq!(rx_count.load(Ordering::SeqCst), 1);
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(tx_count.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn close_works() {
+        let (mut tx, mut rx) = channel();
+        let (waker, count) = counting_waker();
+        assert_eq!(
+            tx.poll_canceled(&mut Context::from_waker(&waker)),
+            Poll::Pending
+        );
+
+        rx.close();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(tx.is_canceled());
+        assert_eq!(tx.send(1), Err(SendError(1)));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn close_after_send_works() {
+        let (tx, mut rx) = channel();
+        tx.send(1).unwrap();
+        rx.close();
+        rx.close();
+        assert_eq!(rx.recv(), Ok(1));
+
+        let (tx, mut rx) = channel();
+        tx.send(1).unwrap();
+        rx.close();
+        drop(rx);
+    }
+
+    #[test]
+    fn unreceived_value_is_dropped() {
+        let value = Arc::new(());
+        let (tx, rx) = channel();
+        tx.send(Arc::clone(&value)).unwrap();
+        assert_eq!(Arc::strong_count(&value), 2);
+        drop(rx);
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        let (tx, mut rx) = channel();
+        rx.close();
+        assert!(tx.send(Arc::clone(&value)).is_err());
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn pool_works() {
+        let pool = Pool::new();
+        let (tx, mut rx) = channel_in(&pool);
+        let cell = tx.0 .0;
+        tx.send(1).unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+
+        // The recycled cell is reused
+        let (tx, mut rx) = channel_in(&pool);
+        assert_eq!(tx.0 .0, cell);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        drop(tx);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+
+        let (tx, mut rx) = channel_in(&pool);
+        assert_eq!(tx.0 .0, cell);
+        let handle = thread::spawn(move || tx.send(2).unwrap());
+        assert_eq!(rx.recv(), Ok(2));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn pool_drops_unreceived_values() {
+        let value = Arc::new(());
+        let pool = Pool::new();
+        let (tx, rx) = channel_in(&pool);
+        tx.send(Arc::clone(&value)).unwrap();
+        drop(rx);
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        // Channels can outlive the pool
+        let (tx, rx) = channel_in(&pool);
+        drop(pool);
+        tx.send(Arc::clone(&value)).unwrap();
+        drop(rx);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn multithread_pool_works() {
+        let pool = Pool::new();
+        for i in 0..1000 {
+            let (tx, mut rx) = channel_in(&pool);
+            thread::spawn(move || tx.send(i).unwrap());
+            assert_eq!(rx.recv(), Ok(i));
+        }
+    }
+
+    #[test]
+    fn slot_works() {
+        let (mut tx, mut rx) = slot();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        for i in 0..3 {
+            assert_eq!(tx.try_send(i), Ok(()));
+            assert_eq!(tx.try_send(i), Err(TrySendError::Full(i)));
+            assert_eq!(rx.try_recv(), Ok(i));
+            assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        }
+
+        tx.try_send(3).unwrap();
+        drop(tx);
+        assert_eq!(rx.try_recv(), Ok(3));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+
+        let (mut tx, rx) = slot();
+        drop(rx);
+        assert!(tx.is_canceled());
+        assert_eq!(tx.try_send(1), Err(TrySendError::Disconnected(1)));
+
+        // Drops the receiver with a pending value
+        let (mut tx, rx) = slot();
+        tx.try_send(1).unwrap();
+        drop(rx);
+        assert!(tx.is_canceled());
+        assert_eq!(tx.try_send(2), Err(TrySendError::Disconnected(2)));
+    }
+
+    #[test]
+    fn slot_poll_works() {
+        let (mut tx, mut rx) = slot();
+        let (waker, count) = counting_waker();
+        let mut cx = Context::from_waker(&waker);
+        for i in 0..3 {
+            assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+            tx.try_send(i).unwrap();
+            assert_eq!(count.load(Ordering::SeqCst), i + 1);
+            assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(Ok(i)));
+        }
+
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+        drop(tx);
+        assert_eq!(count.load(Ordering::SeqCst), 4);
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(Err(RecvError)));
+    }
+
+    #[test]
+    fn slot_receiver_does_not_wait_for_waking_sender() {
+        let (mut tx, mut rx) = slot();
+        let lock = Arc::new(Mutex::new(()));
+        let (waking_tx, waking_rx) = std_mpsc::channel();
+        let waker = Waker::from(Arc::new(LockingWaker {
+            lock: Arc::clone(&lock),
+            waking: Mutex::new(waking_tx),
+        }));
+        let mut cx = Context::from_waker(&waker);
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+
+        // The waker blocks the sender until the receiving side releases the lock.
+        let guard = lock.lock().unwrap();
+        let handle = thread::spawn(move || {
+            tx.try_send(1).unwrap();
+            tx
+        });
+        waking_rx.recv().unwrap();
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(Ok(1)));
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+        drop(guard);
+
+        let mut tx = handle.join().unwrap();
+        tx.try_send(2).unwrap();
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(Ok(2)));
+    }
+
+    struct LockingWaker {
+        lock: Arc<Mutex<()>>,
+        waking: Mutex<std_mpsc::Sender<()>>,
+    }
+    impl Wake for LockingWaker {
+        fn wake(self: Arc<Self>) {
+            let _ = self.waking.lock().unwrap().send(());
+            drop(self.lock.lock().unwrap());
+        }
+    }
+
+    #[test]
+    fn slot_polls_race_with_try_send() {
+        let (mut tx, mut rx) = slot();
+        let handle = thread::spawn(move || {
+            for i in 0..20 {
+                let mut item = i;
+                while let Err(TrySendError::Full(i)) = tx.try_send(item) {
+                    item = i;
+                    thread::yield_now();
+                }
+            }
+        });
+
+        // Every poll re-registers a waker while the sender may be taking the previous one.
+        let (waker, _) = counting_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut expected = 0;
+        loop {
+            match rx.poll_recv(&mut cx) {
+                Poll::Pending => thread::yield_now(),
+                Poll::Ready(Ok(i)) => {
+                    assert_eq!(i, expected);
+                    expected += 1;
+                }
+                Poll::Ready(Err(RecvError)) => break,
+            }
+        }
+        assert_eq!(expected, 20);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn multithread_slot_works() {
+        let (mut tx, mut rx) = slot();
+        let handle = thread::spawn(move || {
+            for i in 0..1000 {
+                let mut item = i;
+                while let Err(TrySendError::Full(i)) = tx.try_send(item) {
+                    item = i;
+                    thread::yield_now();
+                }
+            }
+        });
+        for i in 0..1000 {
+            assert_eq!(rx.recv(), Ok(i));
+        }
+        assert_eq!(rx.recv(), Err(RecvError));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn state_works() {
+        let (tx, mut rx) = channel();
+        assert_eq!(rx.state(), ReceiverState::Empty);
+        assert!(!rx.is_ready());
+        assert!(!rx.is_disconnected());
+
+        tx.send(1).unwrap();
+        assert_eq!(rx.state(), ReceiverState::Ready);
+        assert!(rx.is_ready());
+        assert!(!rx.is_disconnected());
+
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.state(), ReceiverState::AlreadyReceived);
+        assert!(rx.is_disconnected());
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(rx.state(), ReceiverState::AlreadyReceived);
+
+        let (tx, mut rx) = channel::<()>();
+        drop(tx);
+        assert_eq!(rx.state(), ReceiverState::SenderDropped);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(rx.state(), ReceiverState::SenderDropped);
+        assert!(rx.is_disconnected());
+
+        let (tx, mut rx) = channel::<()>();
+        rx.close();
+        assert_eq!(rx.state(), ReceiverState::Closed);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(rx.state(), ReceiverState::Closed);
+        assert!(tx.send(()).is_err());
+        assert_eq!(rx.state(), ReceiverState::Closed);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(rx.state(), ReceiverState::Closed);
+        assert!(rx.is_disconnected());
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn stats_never_decrease() {
+        let (tx, mut rx) = channel();
+        tx.send(1).unwrap();
+        assert_eq!((rx.stats().sent, rx.stats().depth), (1, 1));
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!((rx.stats().sent, rx.stats().received), (1, 1));
+        assert_eq!((rx.stats().depth, rx.stats().peak_depth), (0, 1));
+
+        let (tx, mut rx) = channel();
+        rx.close();
+        assert_eq!(tx.send(1), Err(SendError(1)));
+        for _ in 0..2 {
+            assert_eq!(rx.stats().failed_disconnected, 1);
+            assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        }
+        assert_eq!(rx.stats().failed_disconnected, 1);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn canceled_send_with_is_counted_as_failed() {
+        let (tx, mut rx) = channel::<usize>();
+        rx.close();
+        assert_eq!(
+            tx.send_with(|| panic!("never called")),
+            Err(SendWithError::Canceled)
+        );
+        assert_eq!(rx.stats().failed_disconnected, 1);
+    }
+
+    #[test]
+    fn shared_channel_works() {
+        let (tx, rx0) = shared_channel();
+        let rx1 = rx0.clone();
+        assert_eq!(rx0.try_recv(), Err(TryRecvError::Empty));
+        assert!(!tx.is_canceled());
+
+        tx.send(Arc::new(1)).unwrap();
+        let rx2 = rx1.clone();
+        for rx in [&rx0, &rx1, &rx2] {
+            assert_eq!(rx.try_recv(), Ok(Arc::new(1)));
+            assert_eq!(rx.try_recv(), Ok(Arc::new(1)));
+        }
+        let value = rx0.try_recv().unwrap();
+        drop((rx0, rx1, rx2));
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        let (tx, rx0) = shared_channel::<()>();
+        let rx1 = rx0.clone();
+        drop(tx);
+        assert_eq!(rx0.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(rx1.try_recv(), Err(TryRecvError::Disconnected));
+
+        let (tx, rx) = shared_channel();
+        drop(rx);
+        assert!(tx.is_canceled());
+        assert_eq!(tx.send(1), Err(SendError(1)));
+    }
+
+    #[test]
+    fn send_with_works() {
+        let (tx, mut rx) = channel();
+        assert_eq!(tx.send_with(|| 1), Ok(()));
+        assert_eq!(rx.try_recv(), Ok(1));
+
+        let (tx, mut rx) = channel::<usize>();
+        rx.close();
+        assert_eq!(
+            tx.send_with(|| panic!("never called")),
+            Err(SendWithError::Canceled)
+        );
+
+        let (tx, rx) = channel();
+        let result = tx.send_with(|| {
+            drop(rx);
+            1
+        });
+        assert_eq!(result, Err(SendWithError::Disconnected(1)));
+    }
+
     #[test]
     fn unused() {
         channel::<()>();
@@ -0,0 +1,339 @@
+//! Oneshot channel whose shared cell is never allocated on the heap.
+//!
+//! The receiver of this channel owns the shared cell, and the sender borrows it.
+//! Thus the receiver needs to be pinned (e.g., on the stack or inside a task)
+//! while the halves are communicating, and the lifetime of the sender is bounded by the receiver.
+//!
+//! The blocking methods of the receiver share a waker cached per thread,
+//! so only the first blocking call on each thread allocates.
+//!
+//! Other than the storage of the cell, the channel behaves like the one created
+//! by [`oneshot::channel`](super::channel),
+//! except that the receiver is polled via [`Receiver::poll_recv`] instead of `Future`.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::pin::pin;
+//! use std::thread;
+//! use nbchan::oneshot::pinned;
+//!
+//! let rx = pin!(pinned::Receiver::new());
+//! let rx = rx.into_ref();
+//! let tx = rx.sender().unwrap();
+//!
+//! thread::scope(|s| {
+//!     s.spawn(move || tx.send(10).unwrap());
+//!     assert_eq!(rx.recv(), Ok(10));
+//! });
+//! ```
+use std::cell::Cell;
+use std::fmt;
+use std::future::Future;
+use std::marker::{PhantomData, PhantomPinned};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "metrics")]
+use metrics::ChannelStats;
+
+use super::{
+    Closed, ReceiverState, RecvError, RecvTimeoutError, SendError, SendWithError, Shared,
+    SharedBox, Storage, TryRecvError,
+};
+
+/// The sending-half of a pinned oneshot channel.
+///
+/// This borrows the shared cell owned by the corresponding [`Receiver`].
+#[derive(Debug)]
+pub struct Sender<'a, T: 'a> {
+    inner: super::Sender<T>,
+    _slot: PhantomData<&'a ()>,
+}
+impl<'a, T> Sender<'a, T> {
+    /// Attempts to send a value on this channel, returning it back if it could not be sent.
+    ///
+    /// This method will never block the current thread.
+    pub fn send(self, t: T) -> Result<(), SendError<T>> {
+        self.inner.send(t)
+    }
+
+    /// Sends a value built by `f`, unless the receiver already has dropped (or closed).
+    ///
+    /// See [`oneshot::Sender::send_with`](super::Sender::send_with) for more details.
+    pub fn send_with<F>(self, f: F) -> Result<(), SendWithError<T>>
+    where
+        F: FnOnce() -> T,
+    {
+        self.inner.send_with(f)
+    }
+
+    /// Returns `true` if the receiver has closed, otherwise `false`.
+    pub fn is_canceled(&self) -> bool {
+        self.inner.is_canceled()
+    }
+
+    /// Blocks the current thread until the receiver has closed.
+    pub fn wait_canceled(&mut self) {
+        self.inner.wait_canceled()
+    }
+
+    /// Polls whether the receiver has closed.
+    ///
+    /// If the receiver is still open, the waker of `cx` will be woken when it closes.
+    pub fn poll_canceled(&mut self, cx: &mut Context) -> Poll<()> {
+        self.inner.poll_canceled(cx)
+    }
+
+    /// Returns a future that completes when the receiver has closed.
+    pub fn closed(&mut self) -> Closed<'_, T> {
+        self.inner.closed()
+    }
+}
+
+/// The receiving-half of a pinned oneshot channel, which owns the shared cell.
+///
+/// The sender is obtained via the [`Receiver::sender`] method after pinning the receiver.
+pub struct Receiver<T> {
+    // NOTE: The halves must be dropped before the cell.
+    tx: Cell<Option<super::Sender<T>>>,
+    rx: Cell<Option<super::Receiver<T>>>,
+    connected: Cell<bool>,
+    shared: Shared<T>,
+    _pinned: PhantomPinned,
+}
+impl<T> Receiver<T> {
+    /// Makes a new receiver.
+    pub fn new() -> Self {
+        Receiver {
+            tx: Cell::new(None),
+            rx: Cell::new(None),
+            connected: Cell::new(false),
+            shared: Shared::new(Storage::Borrowed),
+            _pinned: PhantomPinned,
+        }
+    }
+
+    /// Returns the sender of this channel.
+    ///
+    /// Only the first call returns `Some`.
+    pub fn sender<'a>(self: Pin<&'a Self>) -> Option<Sender<'a, T>> {
+        self.connect();
+        let inner = self.tx.take()?;
+        Some(Sender {
+            inner,
+            _slot: PhantomData,
+        })
+    }
+
+    /// Attempts to return a pending value on this receiver without blocking.
+    pub fn try_recv(self: Pin<&Self>) -> Result<T, TryRecvError> {
+        self.with_inner(|rx| rx.try_recv())
+    }
+
+    /// Polls this receiver for the value.
+    ///
+    /// If no value is available, the waker of `cx` will be woken when it is sent or the sender drops.
+    pub fn poll_recv(self: Pin<&Self>, cx: &mut Context) -> Poll<Result<T, RecvError>> {
+        self.with_inner(|rx| Pin::new(rx).poll(cx))
+    }
+
+    /// Blocks the current thread until a value is received or the sender has dropped.
+    pub fn recv(self: Pin<&Self>) -> Result<T, RecvError> {
+        self.with_inner(|rx| rx.recv())
+    }
+
+    /// Blocks the current thread until a value is received, the sender has dropped,
+    /// or `timeout` has elapsed.
+    pub fn recv_timeout(self: Pin<&Self>, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.with_inner(|rx| rx.recv_timeout(timeout))
+    }
+
+    /// Blocks the current thread until a value is received, the sender has dropped,
+    /// or `deadline` is reached.
+    pub fn recv_deadline(self: Pin<&Self>, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        self.with_inner(|rx| rx.recv_deadline(deadline))
+    }
+
+    /// Closes the receiving half of the channel.
+    ///
+    /// See [`oneshot::Receiver::close`](super::Receiver::close) for more details.
+    pub fn close(self: Pin<&Self>) {
+        self.with_inner(|rx| rx.close())
+    }
+
+    /// Returns the current state of this channel, without receiving the value.
+    pub fn state(self: Pin<&Self>) -> ReceiverState {
+        self.with_inner(|rx| rx.state())
+    }
+
+    /// Returns `true` if a value can be received immediately, otherwise `false`.
+    pub fn is_ready(self: Pin<&Self>) -> bool {
+        self.with_inner(|rx| rx.is_ready())
+    }
+
+    /// Returns `true` if no value will ever be received, otherwise `false`.
+    pub fn is_disconnected(self: Pin<&Self>) -> bool {
+        self.with_inner(|rx| rx.is_disconnected())
+    }
+
+    /// Returns a snapshot of the counters of this channel.
+    #[cfg(feature = "metrics")]
+    pub fn stats(self: Pin<&Self>) -> ChannelStats {
+        self.with_inner(|rx| rx.stats())
+    }
+
+    /// Calls `f` with the receiving half, which is moved out of this receiver during the call.
+    ///
+    /// Thus no aliasing `&mut` can be made even if `f` reenters this receiver (e.g., via a waker);
+    /// such a reentrant call panics.
+    fn with_inner<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut super::Receiver<T>) -> R,
+    {
+        self.connect();
+        let mut rx = self
+            .rx
+            .take()
+            .expect("pinned::Receiver cannot be used while it is in use");
+        let result = f(&mut rx);
+        self.rx.set(Some(rx));
+        result
+    }
+
+    fn connect(&self) {
+        if !self.connected.replace(true) {
+            // The receiver is pinned here, so the cell never moves until it drops.
+            let ptr = &self.shared as *const _ as *mut _;
+            self.tx.set(Some(super::Sender(SharedBox(ptr))));
+            self.rx.set(Some(super::Receiver(SharedBox(ptr))));
+        }
+    }
+}
+unsafe impl<T: Send> Send for Receiver<T> {}
+impl<T> Default for Receiver<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Receiver {{ .. }}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::task::Waker;
+    use std::thread;
+
+    use super::*;
+    use park;
+
+    #[test]
+    fn send_and_recv_works() {
+        let rx = pin!(Receiver::new());
+        let rx = rx.into_ref();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+        let tx = rx.sender().unwrap();
+        assert!(rx.sender().is_none());
+        tx.send(1).unwrap();
+        assert_eq!(rx.state(), ReceiverState::Ready);
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.state(), ReceiverState::AlreadyReceived);
+    }
+
+    #[test]
+    fn sender_dropped_works() {
+        let rx = pin!(Receiver::<()>::new());
+        let rx = rx.into_ref();
+        drop(rx.sender().unwrap());
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+
+        // The sender is dropped together with the receiver if it has not been taken
+        let rx = pin!(Receiver::<()>::new());
+        assert_eq!(rx.into_ref().try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn receiver_closed_works() {
+        let rx = pin!(Receiver::new());
+        let rx = rx.into_ref();
+        let tx = rx.sender().unwrap();
+        rx.close();
+        assert!(tx.is_canceled());
+        assert_eq!(tx.send(1), Err(SendError(1)));
+    }
+
+    #[test]
+    fn forwarded_methods_work() {
+        let rx = pin!(Receiver::<()>::new());
+        let rx = rx.into_ref();
+        let mut tx = rx.sender().unwrap();
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(tx.poll_canceled(&mut cx), Poll::Pending);
+        assert!(!rx.is_ready());
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(1)),
+            Err(RecvTimeoutError::Timeout)
+        );
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                tx.wait_canceled();
+                let mut cx = Context::from_waker(Waker::noop());
+                assert_eq!(tx.poll_canceled(&mut cx), Poll::Ready(()));
+            });
+            rx.close();
+        });
+        assert!(rx.is_disconnected());
+    }
+
+    #[test]
+    fn blocking_recv_reuses_thread_waker() {
+        let rx = pin!(Receiver::<()>::new());
+        let rx = rx.into_ref();
+        let waker = park::thread_waker();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(1)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        assert!(waker.will_wake(&park::thread_waker()));
+    }
+
+    #[test]
+    fn unreceived_value_is_dropped() {
+        let value = Arc::new(());
+        {
+            let rx = pin!(Receiver::new());
+            rx.into_ref()
+                .sender()
+                .unwrap()
+                .send(Arc::clone(&value))
+                .unwrap();
+        }
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn multithread_poll_works() {
+        let mut cx = Context::from_waker(Waker::noop());
+        let rx = pin!(Receiver::new());
+        let rx = rx.into_ref();
+        let tx = rx.sender().unwrap();
+        thread::scope(|s| {
+            assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+            s.spawn(move || tx.send(1).unwrap());
+            loop {
+                if let Poll::Ready(v) = rx.poll_recv(&mut cx) {
+                    assert_eq!(v, Ok(1));
+                    break;
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::{ReceiverState, SendError, TryRecvError};
+
+/// Creates a new promise, returning the promise and the receiver of its result.
+///
+/// This is a oneshot channel specialized for carrying `Result<T, E>`.
+/// If the `Promise` drops without being settled, the receiver observes `PromiseError::Broken`.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use nbchan::oneshot::{self, PromiseError};
+///
+/// let (promise, mut rx) = oneshot::promise::<usize, String>();
+/// thread::spawn(move || promise.fulfill(1).unwrap());
+/// assert_eq!(rx.get(), Ok(1));
+///
+/// let (promise, mut rx) = oneshot::promise::<usize, String>();
+/// promise.fail("oops".to_owned()).unwrap();
+/// assert_eq!(rx.try_get(), Err(PromiseError::Failed("oops".to_owned())));
+///
+/// let (promise, mut rx) = oneshot::promise::<usize, String>();
+/// drop(promise);
+/// assert_eq!(rx.try_get(), Err(PromiseError::Broken));
+/// ```
+pub fn promise<T, E>() -> (Promise<T, E>, PromiseReceiver<T, E>) {
+    let (tx, rx) = super::channel();
+    (Promise(tx), PromiseReceiver(rx))
+}
+
+/// The settling-half of a promise.
+///
+/// Dropping this without calling `fulfill` or `fail` breaks the promise.
+#[derive(Debug)]
+pub struct Promise<T, E>(super::Sender<Result<T, E>>);
+impl<T, E> Promise<T, E> {
+    /// Fulfills the promise with `value`, returning it back if the receiver has dropped.
+    ///
+    /// This method will never block the current thread.
+    pub fn fulfill(self, value: T) -> Result<(), SendError<T>> {
+        self.0.send(Ok(value)).map_err(|e| match e.0 {
+            Ok(value) => SendError(value),
+            Err(_) => unreachable!(),
+        })
+    }
+
+    /// Fails the promise with `error`, returning it back if the receiver has dropped.
+    ///
+    /// This method will never block the current thread.
+    pub fn fail(self, error: E) -> Result<(), SendError<E>> {
+        self.0.send(Err(error)).map_err(|e| match e.0 {
+            Err(error) => SendError(error),
+            Ok(_) => unreachable!(),
+        })
+    }
+
+    /// Returns `true` if the receiver has dropped (or closed), otherwise `false`.
+    pub fn is_canceled(&self) -> bool {
+        self.0.is_canceled()
+    }
+}
+
+/// The receiving-half of a promise.
+///
+/// This also implements `Future`, which resolves to the result of the promise.
+#[derive(Debug)]
+pub struct PromiseReceiver<T, E>(super::Receiver<Result<T, E>>);
+impl<T, E> PromiseReceiver<T, E> {
+    /// Attempts to return the result of the promise without blocking.
+    ///
+    /// If the promise has not been settled yet, this returns `PromiseError::Pending`.
+    pub fn try_get(&mut self) -> Result<T, PromiseError<E>> {
+        match self.0.try_recv() {
+            Ok(result) => result.map_err(PromiseError::Failed),
+            Err(TryRecvError::Empty) => Err(PromiseError::Pending),
+            Err(TryRecvError::Disconnected) => Err(self.disconnected()),
+        }
+    }
+
+    /// Blocks the current thread until the promise is settled or broken.
+    pub fn get(&mut self) -> Result<T, PromiseError<E>> {
+        match self.0.recv() {
+            Ok(result) => result.map_err(PromiseError::Failed),
+            Err(_) => Err(self.disconnected()),
+        }
+    }
+
+    fn disconnected(&self) -> PromiseError<E> {
+        if self.0.state() == ReceiverState::AlreadyReceived {
+            PromiseError::AlreadyReceived
+        } else {
+            PromiseError::Broken
+        }
+    }
+}
+impl<T, E> Future for PromiseReceiver<T, E> {
+    type Output = Result<T, PromiseError<E>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.0).poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(result)) => Poll::Ready(result.map_err(PromiseError::Failed)),
+            Poll::Ready(Err(_)) => Poll::Ready(Err(this.disconnected())),
+        }
+    }
+}
+
+/// An error returned from the getters of `PromiseReceiver`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PromiseError<E> {
+    /// The promise has not been settled yet.
+    ///
+    /// This is returned only by `try_get`.
+    Pending,
+
+    /// The promise was failed with the error.
+    Failed(E),
+
+    /// The promise dropped without being settled.
+    Broken,
+
+    /// The result already has been taken by a previous call.
+    AlreadyReceived,
+}
+impl<E: fmt::Display> fmt::Display for PromiseError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PromiseError::Pending => write!(f, "the promise has not been settled yet"),
+            PromiseError::Failed(ref e) => write!(f, "the promise was failed: {}", e),
+            PromiseError::Broken => write!(f, "broken promise"),
+            PromiseError::AlreadyReceived => write!(f, "the result already has been received"),
+        }
+    }
+}
+impl<E: Error> Error for PromiseError<E> {}
+
+#[cfg(test)]
+mod test {
+    use std::task::Waker;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn fulfill_works() {
+        let (tx, mut rx) = promise::<usize, ()>();
+        assert_eq!(rx.try_get(), Err(PromiseError::Pending));
+        tx.fulfill(1).unwrap();
+        assert_eq!(rx.try_get(), Ok(1));
+        assert_eq!(rx.try_get(), Err(PromiseError::AlreadyReceived));
+    }
+
+    #[test]
+    fn fail_works() {
+        let (tx, mut rx) = promise::<(), usize>();
+        thread::spawn(move || tx.fail(1).unwrap());
+        assert_eq!(rx.get(), Err(PromiseError::Failed(1)));
+    }
+
+    #[test]
+    fn broken_promise_works() {
+        let (tx, mut rx) = promise::<(), ()>();
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(Pin::new(&mut rx).poll(&mut cx), Poll::Pending);
+        drop(tx);
+        assert_eq!(
+            Pin::new(&mut rx).poll(&mut cx),
+            Poll::Ready(Err(PromiseError::Broken))
+        );
+    }
+
+    #[test]
+    fn receiver_dropped_works() {
+        let (tx, rx) = promise::<usize, usize>();
+        drop(rx);
+        assert!(tx.is_canceled());
+        assert_eq!(tx.fulfill(1), Err(SendError(1)));
+
+        let (tx, rx) = promise::<usize, usize>();
+        drop(rx);
+        assert_eq!(tx.fail(2), Err(SendError(2)));
+    }
+}
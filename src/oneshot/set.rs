@@ -0,0 +1,375 @@
+use std::fmt;
+#[cfg(feature = "futures")]
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "futures")]
+use futures_core::Stream;
+
+use super::{Receiver, ReceiverState, RecvError, TryRecvError};
+use park;
+use queue::{self, QueueHead, QueueTail};
+
+/// A collection of oneshot receivers, which yields their results in the order of completion.
+///
+/// Each receiver in the set registers a waker that pushes its id onto a lock-free ready queue
+/// when the sender completes (i.e., sends a value or drops),
+/// so retrieving the next completed result takes O(1) amortized time
+/// regardless of the number of receivers.
+///
+/// # Examples
+///
+/// ```
+/// use nbchan::oneshot::{self, RecvError, Set};
+///
+/// let mut set = Set::new();
+/// let mut senders = Vec::new();
+/// for key in 0..3 {
+///     let (tx, rx) = oneshot::channel();
+///     set.insert(key, rx);
+///     senders.push(tx);
+/// }
+/// assert_eq!(set.try_next(), None);
+///
+/// senders.pop().unwrap().send("two").unwrap();
+/// drop(senders.remove(0));
+/// assert_eq!(set.try_next(), Some((2, Ok("two"))));
+/// assert_eq!(set.try_next(), Some((0, Err(RecvError))));
+/// assert_eq!(set.try_next(), None);
+/// assert_eq!(set.len(), 1);
+/// ```
+///
+/// `Set` is also an iterator which blocks the current thread until the next receiver completes,
+/// and ends when the set becomes empty:
+///
+/// ```
+/// use std::thread;
+/// use nbchan::oneshot::{self, Set};
+///
+/// let mut set = Set::new();
+/// for key in 0..3 {
+///     let (tx, rx) = oneshot::channel();
+///     set.insert(key, rx);
+///     thread::spawn(move || tx.send(key * 2).unwrap());
+/// }
+///
+/// let mut results = set.map(|(key, result)| (key, result.unwrap())).collect::<Vec<_>>();
+/// results.sort();
+/// assert_eq!(results, [(0, 0), (1, 2), (2, 4)]);
+/// ```
+pub struct Set<K, T> {
+    entries: Vec<Option<(K, Receiver<T>)>>,
+    free_ids: Vec<usize>,
+    ready_head: QueueHead<usize>,
+    ready_tail: QueueTail<usize>,
+}
+impl<K, T> Set<K, T> {
+    /// Makes a new empty set.
+    pub fn new() -> Self {
+        let (ready_head, ready_tail) = queue::fifo();
+        Set {
+            entries: Vec::new(),
+            free_ids: Vec::new(),
+            ready_head,
+            ready_tail,
+        }
+    }
+
+    /// Adds a receiver identified by `key` to this set.
+    pub fn insert(&mut self, key: K, rx: Receiver<T>) {
+        let id = self.free_ids.pop().unwrap_or(self.entries.len());
+        if id == self.entries.len() {
+            self.entries.push(None);
+        }
+        self.entries[id] = Some((key, rx));
+        self.register(id);
+    }
+
+    /// Returns the key and the result of a completed receiver without blocking.
+    ///
+    /// If none of the receivers has completed, this returns `None`.
+    /// The returned receiver is removed from this set.
+    pub fn try_next(&mut self) -> Option<(K, Result<T, RecvError>)> {
+        while let Some(id) = self.ready_head.dequeue() {
+            let result = match self.entries[id] {
+                Some((_, ref mut rx)) => rx.try_recv(),
+                None => continue,
+            };
+            match result {
+                Err(TryRecvError::Empty) => {
+                    // Spurious wakeup.
+                    self.register(id);
+                }
+                result => {
+                    let (key, _) = self.entries[id].take().expect("Never fails");
+                    self.free_ids.push(id);
+                    return Some((key, result.map_err(|_| RecvError)));
+                }
+            }
+        }
+        None
+    }
+
+    /// Polls for a completed receiver, registering the current task to be woken when one completes.
+    ///
+    /// Returns `Poll::Ready(None)` if this set is empty.
+    pub fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<(K, Result<T, RecvError>)>> {
+        if let Some(next) = self.try_next() {
+            return Poll::Ready(Some(next));
+        }
+        if self.is_empty() {
+            return Poll::Ready(None);
+        }
+        self.ready_head.register_waker(cx.waker());
+        match self.try_next() {
+            None => Poll::Pending,
+            next => Poll::Ready(next),
+        }
+    }
+
+    /// Blocks the current thread until a receiver completes or `timeout` has elapsed.
+    ///
+    /// Returns `None` if this set is empty or the timeout has elapsed.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Option<(K, Result<T, RecvError>)> {
+        self.next_until(Instant::now().checked_add(timeout))
+    }
+
+    /// Blocks the current thread until a receiver completes or `deadline` is reached.
+    ///
+    /// Returns `None` if this set is empty or the deadline has been reached.
+    pub fn next_deadline(&mut self, deadline: Instant) -> Option<(K, Result<T, RecvError>)> {
+        self.next_until(Some(deadline))
+    }
+
+    /// Returns the number of the receivers in this set.
+    pub fn len(&self) -> usize {
+        self.entries.len() - self.free_ids.len()
+    }
+
+    /// Returns `true` if this set has no receivers, otherwise `false`.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn next_until(&mut self, deadline: Option<Instant>) -> Option<(K, Result<T, RecvError>)> {
+        let waker = park::thread_waker();
+        loop {
+            match self.poll_next(&mut Context::from_waker(&waker)) {
+                Poll::Ready(next) => return next,
+                Poll::Pending => {}
+            }
+            if !park::park_until(deadline) {
+                return None;
+            }
+        }
+    }
+
+    fn register(&mut self, id: usize) {
+        let waker = Waker::from(Arc::new(ReadyWaker {
+            id,
+            ready_tail: self.ready_tail.clone(),
+        }));
+        let rx = &self.entries[id].as_ref().expect("Never fails").1;
+        if rx.state() != ReceiverState::Empty || !rx.0.register_rx_task(&waker) {
+            // Already completed (or closed, which no sender will ever wake).
+            self.ready_tail.enqueue(id);
+        }
+    }
+}
+impl<K, T> Iterator for Set<K, T> {
+    type Item = (K, Result<T, RecvError>);
+
+    /// Blocks the current thread until a receiver completes.
+    ///
+    /// Returns `None` if this set is empty.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_until(None)
+    }
+}
+#[cfg(feature = "futures")]
+impl<K: Unpin, T> Stream for Set<K, T> {
+    type Item = (K, Result<T, RecvError>);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        Set::poll_next(self.get_mut(), cx)
+    }
+}
+impl<K, T> Default for Set<K, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<K, T> fmt::Debug for Set<K, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Set {{ len: {} }}", self.len())
+    }
+}
+
+struct ReadyWaker {
+    id: usize,
+    ready_tail: QueueTail<usize>,
+}
+impl Wake for ReadyWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let _ = self.ready_tail.enqueue(self.id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use super::super::channel;
+    use super::*;
+
+    #[test]
+    fn set_works() {
+        let mut set = Set::new();
+        assert!(set.is_empty());
+
+        let (tx0, rx0) = channel();
+        let (tx1, rx1) = channel();
+        set.insert("a", rx0);
+        set.insert("b", rx1);
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.try_next(), None);
+
+        tx1.send(1).unwrap();
+        assert_eq!(set.try_next(), Some(("b", Ok(1))));
+        assert_eq!(set.try_next(), None);
+
+        // Already completed receivers
+        let (tx2, rx2) = channel();
+        tx2.send(2).unwrap();
+        set.insert("c", rx2);
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.try_next(), Some(("c", Ok(2))));
+
+        drop(tx0);
+        assert_eq!(set.try_next(), Some(("a", Err(RecvError))));
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn closed_receiver_works() {
+        let mut set = Set::new();
+        let (tx, mut rx) = channel::<usize>();
+        rx.close();
+        set.insert(0, rx);
+        assert_eq!(
+            set.next_timeout(Duration::from_secs(10)),
+            Some((0, Err(RecvError)))
+        );
+        assert!(tx.send(1).is_err());
+
+        let (tx, mut rx) = channel();
+        rx.close();
+        assert!(tx.send(1).is_err());
+        set.insert(1, rx);
+        assert_eq!(set.try_next(), Some((1, Err(RecvError))));
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn multithread_set_works() {
+        let mut set = Set::new();
+        for i in 0..100 {
+            let (tx, rx) = channel();
+            set.insert(i, rx);
+            thread::spawn(move || tx.send(i * 2).unwrap());
+        }
+
+        let mut values = Vec::new();
+        for (key, result) in &mut set {
+            assert_eq!(result, Ok(key * 2));
+            values.push(key);
+        }
+        values.sort();
+        assert_eq!(values, (0..100).collect::<Vec<_>>());
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn next_timeout_works() {
+        let mut set = Set::new();
+        assert_eq!(set.next_timeout(Duration::from_millis(1)), None);
+
+        let (tx0, rx0) = channel::<usize>();
+        let (tx1, rx1) = channel();
+        set.insert(0, rx0);
+        set.insert(1, rx1);
+        assert_eq!(set.next_timeout(Duration::from_millis(1)), None);
+
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            tx1.send(1).unwrap();
+        });
+        assert_eq!(set.next_timeout(Duration::from_secs(10)), Some((1, Ok(1))));
+        handle.join().unwrap();
+
+        thread::spawn(move || drop(tx0));
+        assert_eq!(set.next(), Some((0, Err(RecvError))));
+        assert_eq!(set.next(), None);
+    }
+
+    #[test]
+    fn poll_next_works() {
+        use std::sync::atomic::Ordering;
+        use test_util::counting_waker;
+
+        let (waker, woken) = counting_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut set = Set::new();
+        assert_eq!(set.poll_next(&mut cx), Poll::Ready(None));
+
+        let (tx, rx) = channel();
+        set.insert("a", rx);
+        assert_eq!(set.poll_next(&mut cx), Poll::Pending);
+        assert_eq!(woken.load(Ordering::SeqCst), 0);
+
+        let handle = thread::spawn(move || tx.send(1).unwrap());
+        handle.join().unwrap();
+        assert_eq!(woken.load(Ordering::SeqCst), 1);
+        assert_eq!(set.poll_next(&mut cx), Poll::Ready(Some(("a", Ok(1)))));
+        assert_eq!(set.poll_next(&mut cx), Poll::Ready(None));
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn stream_works() {
+        let mut set = Set::new();
+        for i in 0..3 {
+            let (tx, rx) = channel();
+            set.insert(i, rx);
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(5));
+                tx.send(i).unwrap();
+            });
+        }
+
+        let waker = park::thread_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut values = Vec::new();
+        loop {
+            match Stream::poll_next(Pin::new(&mut set), &mut cx) {
+                Poll::Pending => {
+                    park::park_until(None);
+                }
+                Poll::Ready(Some((key, result))) => {
+                    assert_eq!(result, Ok(key));
+                    values.push(key);
+                }
+                Poll::Ready(None) => break,
+            }
+        }
+        values.sort();
+        assert_eq!(values, [0, 1, 2]);
+    }
+}
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+use std::task::{Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::Instant;
+
+thread_local! {
+    static THREAD_WAKER: Waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+}
+
+/// Returns a waker that unparks the current thread.
+///
+/// The waker is allocated once per thread, and then only its reference count is updated.
+pub fn thread_waker() -> Waker {
+    THREAD_WAKER
+        .try_with(Waker::clone)
+        // The thread is being torn down.
+        .unwrap_or_else(|_| Waker::from(Arc::new(ThreadWaker(thread::current()))))
+}
+
+/// Blocks the current thread until it is unparked or `deadline` is reached.
+///
+/// Returns `false` if `deadline` already has passed.
+pub fn park_until(deadline: Option<Instant>) -> bool {
+    if let Some(deadline) = deadline {
+        let now = Instant::now();
+        if now >= deadline {
+            return false;
+        }
+        thread::park_timeout(deadline - now);
+    } else {
+        thread::park();
+    }
+    true
+}
+
+#[derive(Debug)]
+struct ThreadWaker(Thread);
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
@@ -1,20 +1,31 @@
 use std::mem;
 use std::ptr;
-use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::task::Waker;
+
+use waker::AtomicWaker;
 
 /// Lock-free FIFO queue.
 #[inline]
 pub fn fifo<T>() -> (QueueHead<T>, QueueTail<T>) {
     let initial = Box::into_raw(Box::new(NodeRef::null()));
     let tail = QueueTail::new(initial);
-    let head = QueueHead::new(initial, Arc::clone(&tail.tail));
+    let head = QueueHead::new(initial, Arc::clone(&tail.shared));
     (head, tail)
 }
 
+/// The state shared by the head and the tails of a queue.
+#[derive(Debug)]
+struct Shared<T> {
+    tail: AtomicPtr<NodeRef<T>>,
+    tail_count: AtomicUsize,
+    head_task: AtomicWaker,
+}
+
 #[derive(Debug)]
 pub struct QueueTail<T> {
-    tail: Arc<AtomicPtr<NodeRef<T>>>,
+    shared: Arc<Shared<T>>,
 }
 impl<T> QueueTail<T> {
     #[inline]
@@ -23,6 +34,11 @@ impl<T> QueueTail<T> {
         if let Some(current_tail) = self.replace_tail(next) {
             let node = Box::into_raw(Box::new(Node { item, next }));
             unsafe { &*current_tail }.store(node);
+
+            // The head registers its waker only when it found the queue empty,
+            // and unregisters it once it has dequeued,
+            // so this is a plain atomic load unless a consumer is waiting.
+            self.shared.head_task.wake();
             None
         } else {
             mem::drop(unsafe { Box::from_raw(next) });
@@ -30,26 +46,65 @@ impl<T> QueueTail<T> {
         }
     }
 
+    /// Links all the items of `batch` to the queue at once.
+    ///
+    /// If the head has dropped, the batch is returned back (even if it is empty).
+    pub fn enqueue_batch(&self, mut batch: Batch<T>) -> Result<(), Batch<T>> {
+        if batch.len == 0 {
+            return if self.is_disconnected() {
+                Err(batch)
+            } else {
+                Ok(())
+            };
+        }
+        if let Some(current_tail) = self.replace_tail(batch.last) {
+            unsafe { &*current_tail }.store(batch.first);
+            batch.first = ptr::null_mut();
+            batch.len = 0;
+            self.shared.head_task.wake();
+            Ok(())
+        } else {
+            Err(batch)
+        }
+    }
+
     #[inline]
     pub fn is_disconnected(&self) -> bool {
-        self.tail.load(Ordering::SeqCst).is_null()
+        self.shared.tail.load(Ordering::SeqCst).is_null()
+    }
+
+    /// Returns a handle which does not keep the head from observing that all the tails have dropped.
+    #[inline]
+    pub fn downgrade(&self) -> WeakQueueTail<T> {
+        WeakQueueTail {
+            shared: Arc::clone(&self.shared),
+        }
     }
 
     #[inline]
     fn new(tail: *mut NodeRef<T>) -> Self {
         QueueTail {
-            tail: Arc::new(AtomicPtr::new(tail)),
+            shared: Arc::new(Shared {
+                tail: AtomicPtr::new(tail),
+                tail_count: AtomicUsize::new(1),
+                head_task: AtomicWaker::new(),
+            }),
         }
     }
 
     #[inline]
     fn replace_tail(&self, new_tail: *mut NodeRef<T>) -> Option<*mut NodeRef<T>> {
         loop {
-            let old = self.tail.load(Ordering::SeqCst);
+            let old = self.shared.tail.load(Ordering::SeqCst);
             if old.is_null() {
                 return None;
             }
-            if self.tail.compare_and_swap(old, new_tail, Ordering::SeqCst) == old {
+            if self
+                .shared
+                .tail
+                .compare_exchange(old, new_tail, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
                 return Some(old);
             }
         }
@@ -59,8 +114,53 @@ unsafe impl<T: Send> Send for QueueTail<T> {}
 unsafe impl<T: Send> Sync for QueueTail<T> {}
 impl<T> Clone for QueueTail<T> {
     fn clone(&self) -> Self {
+        self.shared.tail_count.fetch_add(1, Ordering::SeqCst);
         QueueTail {
-            tail: Arc::clone(&self.tail),
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+impl<T> Drop for QueueTail<T> {
+    fn drop(&mut self) {
+        if self.shared.tail_count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.shared.head_task.wake();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct WeakQueueTail<T> {
+    shared: Arc<Shared<T>>,
+}
+impl<T> WeakQueueTail<T> {
+    /// Returns a new tail, unless all the (strong) tails have dropped.
+    #[inline]
+    pub fn upgrade(&self) -> Option<QueueTail<T>> {
+        let mut count = self.shared.tail_count.load(Ordering::SeqCst);
+        while count != 0 {
+            match self.shared.tail_count.compare_exchange(
+                count,
+                count + 1,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => {
+                    return Some(QueueTail {
+                        shared: Arc::clone(&self.shared),
+                    })
+                }
+                Err(c) => count = c,
+            }
+        }
+        None
+    }
+}
+unsafe impl<T: Send> Send for WeakQueueTail<T> {}
+unsafe impl<T: Send> Sync for WeakQueueTail<T> {}
+impl<T> Clone for WeakQueueTail<T> {
+    fn clone(&self) -> Self {
+        WeakQueueTail {
+            shared: Arc::clone(&self.shared),
         }
     }
 }
@@ -68,7 +168,10 @@ impl<T> Clone for QueueTail<T> {
 #[derive(Debug)]
 pub struct QueueHead<T> {
     head: *mut NodeRef<T>,
-    tail: Arc<AtomicPtr<NodeRef<T>>>,
+    shared: Arc<Shared<T>>,
+
+    // The last tail at the time of `close()` (or null if the head is open).
+    closed_tail: *mut NodeRef<T>,
 }
 impl<T> QueueHead<T> {
     #[inline]
@@ -83,27 +186,179 @@ impl<T> QueueHead<T> {
         }
     }
 
+    /// Dequeues at most `max` items into `buf`, returning the number of the dequeued items.
+    #[inline]
+    pub fn dequeue_many(&mut self, buf: &mut Vec<T>, max: usize) -> usize {
+        let mut count = 0;
+        while count < max {
+            match unsafe { &*self.head }.load() {
+                None => break,
+                Some(node) => {
+                    mem::drop(unsafe { Box::from_raw(self.head) });
+                    self.head = node.next;
+                    buf.push(node.item);
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// Returns the number of the items linked to the queue, by walking them.
+    pub fn pending_count(&self) -> usize {
+        let mut count = 0;
+        let mut node_ref = self.head;
+        loop {
+            let node = unsafe { &*node_ref }.0.load(Ordering::SeqCst);
+            if node.is_null() {
+                return count;
+            }
+            count += 1;
+            node_ref = unsafe { (*node).next };
+        }
+    }
+
     #[inline]
     pub fn is_tail_alive(&self) -> bool {
-        Arc::strong_count(&self.tail) > 1
+        self.tail_count() > 0
+    }
+
+    #[inline]
+    pub fn tail_count(&self) -> usize {
+        self.shared.tail_count.load(Ordering::SeqCst)
+    }
+
+    /// Rejects any further enqueuing, keeping the items already linked to the queue.
+    #[inline]
+    pub fn close(&mut self) {
+        if self.closed_tail.is_null() {
+            self.closed_tail = self.shared.tail.swap(ptr::null_mut(), Ordering::SeqCst);
+        }
+    }
+
+    /// Closes the queue and drops all the items linked to it, returning the number of them.
+    pub fn clear(&mut self) -> usize {
+        self.close();
+        let tail = self.closed_tail;
+        let mut count = 0;
+        while self.head != tail {
+            if self.dequeue().is_some() {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Returns `true` if no more items will be dequeued, otherwise `false`.
+    ///
+    /// Note that an item enqueued just before the last tail dropped may still be dequeued
+    /// after this returned `true`.
+    #[inline]
+    pub fn is_disconnected(&self) -> bool {
+        if self.closed_tail.is_null() {
+            !self.is_tail_alive()
+        } else {
+            // Enqueuings which had won the race against `close()` may be in progress
+            // until the head reaches the closed tail.
+            self.head == self.closed_tail
+        }
+    }
+
+    /// Registers `waker` to be woken by the next enqueuing or by the drop of the last tail.
+    ///
+    /// The caller should try dequeuing again after this call.
+    #[inline]
+    pub fn register_waker(&self, waker: &Waker) {
+        self.shared.head_task.register(waker);
     }
 
+    /// Drops the waker registered by `register_waker` (if it has not been woken yet).
+    ///
+    /// The caller should call this once it has dequeued an item after registering,
+    /// so that the following enqueuings do not wake the stale waker.
     #[inline]
-    fn new(head: *mut NodeRef<T>, tail: Arc<AtomicPtr<NodeRef<T>>>) -> Self {
-        QueueHead { head, tail }
+    pub fn unregister_waker(&self) {
+        self.shared.head_task.unregister();
+    }
+
+    #[inline]
+    fn new(head: *mut NodeRef<T>, shared: Arc<Shared<T>>) -> Self {
+        QueueHead {
+            head,
+            shared,
+            closed_tail: ptr::null_mut(),
+        }
     }
 }
 unsafe impl<T: Send> Send for QueueHead<T> {}
 impl<T> Drop for QueueHead<T> {
     fn drop(&mut self) {
-        let tail = self.tail.swap(ptr::null_mut(), Ordering::SeqCst);
-        while self.head != tail {
-            let _ = self.dequeue();
-        }
+        self.clear();
         mem::drop(unsafe { Box::from_raw(self.head) });
     }
 }
 
+/// A chain of nodes which has not been linked to any queue yet.
+#[derive(Debug)]
+pub struct Batch<T> {
+    first: *mut Node<T>,
+    last: *mut NodeRef<T>,
+    len: usize,
+}
+impl<T> Batch<T> {
+    pub fn new<I>(items: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut batch = Batch {
+            first: ptr::null_mut(),
+            last: ptr::null_mut(),
+            len: 0,
+        };
+        for item in items {
+            let next = Box::into_raw(Box::new(NodeRef::null()));
+            let node = Box::into_raw(Box::new(Node { item, next }));
+            if batch.first.is_null() {
+                batch.first = node;
+            } else {
+                unsafe { &*batch.last }.store(node);
+            }
+            batch.last = next;
+            batch.len += 1;
+        }
+        batch
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn into_vec(mut self) -> Vec<T> {
+        let mut items = Vec::with_capacity(self.len);
+        while let Some(item) = self.pop_front() {
+            items.push(item);
+        }
+        items
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        if self.first.is_null() {
+            return None;
+        }
+        let node = unsafe { *Box::from_raw(self.first) };
+        let next = unsafe { Box::from_raw(node.next) };
+        self.first = next.0.load(Ordering::SeqCst);
+        self.len -= 1;
+        Some(node.item)
+    }
+}
+impl<T> Drop for Batch<T> {
+    fn drop(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+}
+
 #[derive(Debug)]
 struct NodeRef<T>(AtomicPtr<Node<T>>);
 impl<T> NodeRef<T> {
@@ -141,6 +396,7 @@ mod test {
     use super::*;
     use std::mem;
     use std::thread;
+    use test_util::counting_waker;
 
     #[test]
     fn single_enqueuing_works() {
@@ -151,6 +407,23 @@ mod test {
         assert_eq!(head.dequeue(), Some(1));
     }
 
+    #[test]
+    fn unregistered_waker_is_not_woken() {
+        let (mut head, tail) = fifo();
+        let (waker, count) = counting_waker();
+        head.register_waker(&waker);
+        tail.enqueue(1);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        tail.enqueue(2);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        head.register_waker(&waker);
+        assert_eq!(head.dequeue(), Some(1));
+        head.unregister_waker();
+        tail.enqueue(3);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
     #[test]
     fn single_producer_works() {
         let (mut head, tail) = fifo();
@@ -186,6 +459,39 @@ mod test {
         assert_eq!(values, (0..100000).collect::<Vec<_>>());
     }
 
+    #[test]
+    fn dequeue_many_works() {
+        let (mut head, tail) = fifo();
+        for i in 0..5 {
+            tail.enqueue(i);
+        }
+
+        let mut buf = Vec::new();
+        assert_eq!(head.dequeue_many(&mut buf, 3), 3);
+        assert_eq!(head.dequeue_many(&mut buf, 3), 2);
+        assert_eq!(head.dequeue_many(&mut buf, 3), 0);
+        assert_eq!(buf, [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn enqueue_batch_works() {
+        let (mut head, tail) = fifo();
+        tail.enqueue(0);
+        assert!(tail.enqueue_batch(Batch::new(1..4)).is_ok());
+        assert!(tail.enqueue_batch(Batch::new(None)).is_ok());
+        tail.enqueue(4);
+        for i in 0..5 {
+            assert_eq!(head.dequeue(), Some(i));
+        }
+        assert_eq!(head.dequeue(), None);
+
+        mem::drop(head);
+        let batch = tail.enqueue_batch(Batch::new(vec![1, 2])).unwrap_err();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch.into_vec(), [1, 2]);
+        assert!(tail.enqueue_batch(Batch::new(None)).is_err());
+    }
+
     #[test]
     fn consumer_dropped_works() {
         let (head, tail) = fifo();
@@ -205,4 +511,32 @@ mod test {
         assert!(!head.is_tail_alive());
         assert_eq!(head.dequeue(), None);
     }
+
+    #[test]
+    fn close_works() {
+        let (mut head, tail) = fifo();
+        assert_eq!(tail.enqueue(1), None);
+        head.close();
+        assert_eq!(tail.enqueue(2), Some(2));
+        assert!(tail.is_disconnected());
+
+        assert!(!head.is_disconnected());
+        assert_eq!(head.dequeue(), Some(1));
+        assert!(head.is_disconnected());
+        assert_eq!(head.dequeue(), None);
+    }
+
+    #[test]
+    fn weak_tail_works() {
+        let (head, tail) = fifo::<()>();
+        let weak = tail.downgrade();
+        assert_eq!(head.tail_count(), 1);
+
+        let upgraded = weak.upgrade().unwrap();
+        assert_eq!(head.tail_count(), 2);
+        mem::drop(tail);
+        mem::drop(upgraded);
+        assert!(!head.is_tail_alive());
+        assert!(weak.upgrade().is_none());
+    }
 }
@@ -0,0 +1,338 @@
+//! Request/response channel built on top of `mpsc` and `oneshot` channels.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::thread;
+//! use nbchan::rpc;
+//!
+//! let (client, server) = rpc::channel::<usize, usize>();
+//!
+//! // Server
+//! thread::spawn(move || {
+//!     for (req, responder) in server.iter() {
+//!         let _ = responder.reply(req * 2);
+//!     }
+//! });
+//!
+//! // Client
+//! let mut response = client.call(10).unwrap();
+//! assert_eq!(response.recv(), Ok(20));
+//! ```
+#[doc(no_inline)]
+pub use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError, TryRecvError};
+
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use mpsc;
+use oneshot::{self, ReceiverState};
+
+/// Creates a new request/response channel, returning the client/server halves.
+pub fn channel<Req, Resp>() -> (Client<Req, Resp>, Server<Req, Resp>) {
+    let (tx, rx) = mpsc::channel();
+    (Client { tx }, Server { rx })
+}
+
+/// The client-half of a request/response channel.
+pub struct Client<Req, Resp> {
+    tx: mpsc::Sender<(Req, Responder<Resp>)>,
+}
+impl<Req, Resp> Client<Req, Resp> {
+    /// Sends a request to the server, returning the receiver of the response.
+    ///
+    /// If the server has dropped, the request is returned back.
+    ///
+    /// If the server drops the corresponding `Responder` without replying,
+    /// the returned receiver reports `CallError::Dropped`.
+    ///
+    /// This method will never block the current thread.
+    pub fn call(&self, req: Req) -> Result<Response<Resp>, SendError<Req>> {
+        let (tx, rx) = oneshot::channel();
+        if let Err(SendError((req, _))) = self.tx.send((req, Responder(tx))) {
+            Err(SendError(req))
+        } else {
+            Ok(Response(rx))
+        }
+    }
+
+    /// Returns `true` if the server has dropped, otherwise `false`.
+    pub fn is_disconnected(&self) -> bool {
+        self.tx.is_disconnected()
+    }
+}
+impl<Req, Resp> Clone for Client<Req, Resp> {
+    fn clone(&self) -> Self {
+        Client {
+            tx: self.tx.clone(),
+        }
+    }
+}
+impl<Req, Resp> fmt::Debug for Client<Req, Resp> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Client {{ .. }}")
+    }
+}
+
+/// The server-half of a request/response channel.
+pub struct Server<Req, Resp> {
+    rx: mpsc::Receiver<(Req, Responder<Resp>)>,
+}
+impl<Req, Resp> Server<Req, Resp> {
+    /// Attempts to return a pending request and its responder without blocking.
+    pub fn try_recv(&self) -> Result<(Req, Responder<Resp>), TryRecvError> {
+        self.rx.try_recv()
+    }
+
+    /// Blocks the current thread until a request arrives or all the clients have dropped.
+    pub fn recv(&self) -> Result<(Req, Responder<Resp>), RecvError> {
+        self.rx.recv()
+    }
+
+    /// Blocks the current thread until a request arrives, all the clients have dropped,
+    /// or `timeout` has elapsed.
+    pub fn recv_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<(Req, Responder<Resp>), RecvTimeoutError> {
+        self.rx.recv_timeout(timeout)
+    }
+
+    /// Polls for a request, registering the current task to be woken when one arrives.
+    ///
+    /// Returns `Poll::Ready(Err(RecvError))` once all the clients have dropped
+    /// and no requests are pending.
+    pub fn poll_recv(&self, cx: &mut Context) -> Poll<Result<(Req, Responder<Resp>), RecvError>> {
+        self.rx.poll_recv(cx)
+    }
+
+    /// Returns an iterator that blocks waiting for requests until all the clients have dropped.
+    pub fn iter(&self) -> mpsc::Iter<'_, (Req, Responder<Resp>)> {
+        self.rx.iter()
+    }
+}
+impl<Req, Resp> fmt::Debug for Server<Req, Resp> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Server {{ .. }}")
+    }
+}
+
+/// The receiver of the response to a request.
+///
+/// This is created by the [`Client::call`] method.
+/// `Response` also implements `Future`, which resolves to the response.
+#[derive(Debug)]
+pub struct Response<Resp>(oneshot::Receiver<Resp>);
+impl<Resp> Response<Resp> {
+    /// Attempts to return the response without blocking.
+    ///
+    /// This fails with `CallError::Empty` if the response has not arrived yet,
+    /// and with `CallError::Dropped` if it never will.
+    pub fn try_recv(&mut self) -> Result<Resp, CallError> {
+        self.0.try_recv().map_err(|e| match e {
+            TryRecvError::Empty => CallError::Empty,
+            TryRecvError::Disconnected => self.disconnected(),
+        })
+    }
+
+    /// Blocks the current thread until the response arrives or the responder has dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nbchan::rpc::{self, CallError};
+    ///
+    /// let (client, server) = rpc::channel::<usize, usize>();
+    /// let mut response = client.call(1).unwrap();
+    /// drop(server);
+    /// assert_eq!(response.recv(), Err(CallError::Dropped));
+    /// ```
+    pub fn recv(&mut self) -> Result<Resp, CallError> {
+        self.0.recv().map_err(|_| self.disconnected())
+    }
+
+    /// Blocks the current thread until the response arrives, the responder has dropped,
+    /// or `timeout` has elapsed.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Resp, CallError> {
+        self.0.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => CallError::Timeout,
+            RecvTimeoutError::Disconnected => self.disconnected(),
+        })
+    }
+
+    fn disconnected(&self) -> CallError {
+        if self.0.state() == ReceiverState::AlreadyReceived {
+            CallError::AlreadyReceived
+        } else {
+            CallError::Dropped
+        }
+    }
+}
+impl<Resp> Future for Response<Resp> {
+    type Output = Result<Resp, CallError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.0).poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(resp)) => Poll::Ready(Ok(resp)),
+            Poll::Ready(Err(_)) => Poll::Ready(Err(this.disconnected())),
+        }
+    }
+}
+
+/// An error returned from the methods of [`Response`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CallError {
+    /// The response has not arrived yet.
+    ///
+    /// This is returned only by `Response::try_recv`.
+    Empty,
+
+    /// The response did not arrive before the timeout elapsed.
+    ///
+    /// This is returned only by `Response::recv_timeout`.
+    Timeout,
+
+    /// The server dropped the responder (or the request) without replying.
+    Dropped,
+
+    /// The response already has been received.
+    AlreadyReceived,
+}
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CallError::Empty => write!(f, "response has not arrived yet"),
+            CallError::Timeout => write!(f, "timed out waiting on response"),
+            CallError::Dropped => write!(f, "request was dropped without reply"),
+            CallError::AlreadyReceived => write!(f, "response already has been received"),
+        }
+    }
+}
+impl Error for CallError {}
+
+/// The handle to reply to a request.
+///
+/// If this is dropped without replying, the caller is notified of it.
+#[derive(Debug)]
+pub struct Responder<Resp>(oneshot::Sender<Resp>);
+impl<Resp> Responder<Resp> {
+    /// Sends the response to the caller, returning it back if the caller has gone.
+    ///
+    /// This method will never block the current thread.
+    pub fn reply(self, resp: Resp) -> Result<(), SendError<Resp>> {
+        self.0.send(resp)
+    }
+
+    /// Returns `true` if the caller has dropped the receiver of the response, otherwise `false`.
+    pub fn is_canceled(&self) -> bool {
+        self.0.is_canceled()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::task::Waker;
+    use std::thread;
+
+    #[test]
+    fn call_works() {
+        let (client, server) = channel();
+        let mut rx = client.call(1).unwrap();
+        assert_eq!(rx.try_recv(), Err(CallError::Empty));
+
+        let (req, responder) = server.try_recv().unwrap();
+        assert!(!responder.is_canceled());
+        responder.reply(req + 1).unwrap();
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Err(CallError::AlreadyReceived));
+        assert_eq!(rx.recv(), Err(CallError::AlreadyReceived));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(1)),
+            Err(CallError::AlreadyReceived)
+        );
+        assert_eq!(server.try_recv().err(), Some(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn responder_dropped_works() {
+        let (client, server) = channel::<usize, usize>();
+        let mut rx = client.call(1).unwrap();
+
+        let (_, responder) = server.try_recv().unwrap();
+        drop(responder);
+        assert_eq!(rx.try_recv(), Err(CallError::Dropped));
+
+        let mut rx = client.call(2).unwrap();
+        let handle = thread::spawn(move || {
+            let (_, responder) = server.try_recv().unwrap();
+            drop(responder);
+            server
+        });
+        assert_eq!(rx.recv(), Err(CallError::Dropped));
+        let server = handle.join().unwrap();
+
+        // The pending requests are dropped together with the server
+        let mut rx = client.call(3).unwrap();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(1)),
+            Err(CallError::Timeout)
+        );
+        drop(server);
+        let mut cx = Context::from_waker(Waker::noop());
+        assert_eq!(
+            Pin::new(&mut rx).poll(&mut cx),
+            Poll::Ready(Err(CallError::Dropped))
+        );
+    }
+
+    #[test]
+    fn blocking_server_works() {
+        let (client, server) = channel::<usize, usize>();
+        assert_eq!(
+            server.recv_timeout(Duration::from_millis(1)).err(),
+            Some(RecvTimeoutError::Timeout)
+        );
+
+        let handle = thread::spawn(move || {
+            let mut rx = client.call(1).unwrap();
+            assert_eq!(rx.recv(), Ok(2));
+        });
+        let (req, responder) = server.recv().unwrap();
+        responder.reply(req + 1).unwrap();
+        handle.join().unwrap();
+
+        assert_eq!(server.recv().err(), Some(RecvError));
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(matches!(
+            server.poll_recv(&mut cx),
+            Poll::Ready(Err(RecvError))
+        ));
+    }
+
+    #[test]
+    fn server_dropped_works() {
+        let (client, server) = channel::<usize, usize>();
+        drop(server);
+        assert!(client.is_disconnected());
+        assert_eq!(client.call(1).err(), Some(SendError(1)));
+    }
+
+    #[test]
+    fn caller_dropped_works() {
+        let (client, server) = channel::<usize, usize>();
+        drop(client.call(1).unwrap());
+
+        let (_, responder) = server.try_recv().unwrap();
+        assert!(responder.is_canceled());
+        assert_eq!(responder.reply(2), Err(SendError(2)));
+        drop(client);
+        assert_eq!(server.try_recv().err(), Some(TryRecvError::Disconnected));
+    }
+}
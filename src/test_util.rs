@@ -0,0 +1,18 @@
+//! Helpers shared by the unit tests.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Wake, Waker};
+
+struct CountingWaker(Arc<AtomicUsize>);
+impl Wake for CountingWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Returns a waker which counts how many times it has been woken.
+pub fn counting_waker() -> (Waker, Arc<AtomicUsize>) {
+    let count = Arc::new(AtomicUsize::new(0));
+    let waker = Waker::from(Arc::new(CountingWaker(Arc::clone(&count))));
+    (waker, count)
+}
@@ -0,0 +1,107 @@
+use std::cell::UnsafeCell;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::Waker;
+
+/// The registering side is writing the waker.
+const REGISTERING: usize = 0b001;
+
+/// A waking side is taking the waker.
+const WAKING: usize = 0b010;
+
+/// A waker has been registered and not been woken yet.
+const REGISTERED: usize = 0b100;
+
+/// A slot holding the waker of a single waiting task.
+///
+/// Only one side may call `register` at a time, while `wake` can be called from anywhere.
+/// If no waker is registered, `wake` costs only an atomic load.
+#[derive(Debug)]
+pub struct AtomicWaker {
+    state: AtomicUsize,
+    waker: UnsafeCell<Option<Waker>>,
+}
+impl AtomicWaker {
+    pub fn new() -> Self {
+        AtomicWaker {
+            state: AtomicUsize::new(0),
+            waker: UnsafeCell::new(None),
+        }
+    }
+
+    /// Registers `waker` to be woken by the next `wake` call.
+    ///
+    /// The caller must check the condition it waits for again after this call,
+    /// because a `wake` call that preceded the registration is not remembered.
+    pub fn register(&self, waker: &Waker) {
+        let mut state = self.state.load(Ordering::SeqCst);
+        loop {
+            if state & WAKING != 0 {
+                // Someone is waking the previous waker; lets the caller retry immediately.
+                waker.wake_by_ref();
+                return;
+            }
+            match self.state.compare_exchange(
+                state,
+                state | REGISTERING,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => break,
+                Err(s) => state = s,
+            }
+        }
+
+        unsafe { *self.waker.get() = Some(waker.clone()) };
+        let registering = state | REGISTERING;
+        let registered = state | REGISTERED;
+        if self
+            .state
+            .compare_exchange(registering, registered, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            // `wake` was called during the registration.
+            let waker = unsafe { (*self.waker.get()).take() };
+            self.state.store(0, Ordering::SeqCst);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Drops the registered waker (if any), so that following `wake` calls cost only an atomic load.
+    ///
+    /// Like `register`, only the registering side may call this.
+    pub fn unregister(&self) {
+        if self
+            .state
+            .compare_exchange(REGISTERED, REGISTERING, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            let waker = unsafe { (*self.waker.get()).take() };
+
+            // A `wake` call during the above leaves `WAKING` set, which is cleared here too.
+            self.state.store(0, Ordering::SeqCst);
+            mem::drop(waker);
+        }
+    }
+
+    /// Wakes the registered waker (if any).
+    #[inline]
+    pub fn wake(&self) {
+        if self.state.load(Ordering::SeqCst) & (REGISTERING | REGISTERED) == 0 {
+            return;
+        }
+        let old = self.state.fetch_or(WAKING, Ordering::SeqCst);
+        if old & (REGISTERING | WAKING) == 0 {
+            let waker = unsafe { (*self.waker.get()).take() };
+            self.state
+                .fetch_and(!(WAKING | REGISTERED), Ordering::SeqCst);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+}
+unsafe impl Send for AtomicWaker {}
+unsafe impl Sync for AtomicWaker {}